@@ -0,0 +1,55 @@
+use ptree::PTree;
+use std::collections::HashMap;
+use std::io;
+
+/// A persistent table of redirects, mapping the url of a redirect (e.g. "/wiki/USA") to the url of
+/// the page it leads to ("/wiki/United_States"). It lives next to the page cache.
+pub struct AliasTable {
+    tree: PTree<String, String>
+}
+
+impl AliasTable {
+    /// Opens the alias table at path, creating it if it doesn't exist yet.
+    pub fn open(path: &str) -> Result<AliasTable, io::Error> {
+        let tree;
+        check!(PTree::<String, String>::open_or_new(path), tree);
+        Ok(AliasTable { tree: tree })
+    }
+
+    /// Returns the canonical url for url, which is just url if it isn't an alias.
+    pub fn resolve(&mut self, url: &str) -> String {
+        match self.tree.search(&url.to_string()) {
+            Ok(Some(canonical)) => canonical,
+            _ => url.to_string()
+        }
+    }
+
+    /// Records that alias leads to canonical. Aliases that are already known are left alone.
+    pub fn insert(&mut self, alias: &str, canonical: &str) -> Result<(), io::Error> {
+        if alias == canonical { return Ok(()) }
+        let alias = alias.to_string();
+        let known;
+        check!(self.tree.contains_key(&alias), known);
+        if known { return Ok(()) }
+        self.tree.insert(&alias, &canonical.to_string())
+    }
+
+    /// Reads the whole table into memory.
+    pub fn all(&mut self) -> Result<HashMap<String, String>, io::Error> {
+        let keys;
+        check!(self.tree.keys(), keys);
+        let mut map = HashMap::with_capacity(keys.len());
+        for alias in keys.into_iter() {
+            let canonical;
+            check!(self.tree.search(&alias), canonical);
+            if let Some(canonical) = canonical {
+                map.insert(alias, canonical);
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.tree.flush()
+    }
+}
@@ -0,0 +1,375 @@
+use content::ContentFilter;
+use landmarks;
+use similarity;
+use link;
+use politeness::Politeness;
+use tokenizer;
+use tokenizer::TokenizerConfig;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::time::Duration;
+
+/// The page to begin from if no seeds are given.
+pub const DEFAULT_SEED: &'static str = "/wiki/Astronomy";
+
+/// The number of pages to be loaded and stuck into the graph if no limit is given.
+pub const DEFAULT_MAX_PAGES: i32 = 50000;
+
+pub const DEFAULT_THREADS: i32 = 32;
+
+pub const DEFAULT_BASE_URL: &'static str = "https://en.wikipedia.org";
+
+pub const DEFAULT_DATA_DIR: &'static str = "data";
+
+pub const DEFAULT_LISTEN: &'static str = "localhost:1243";
+
+pub const USAGE: &'static str = "\
+usage: WikipediaReader <command>... [options]
+
+commands:
+    clean               remove everything in the data directory
+    import <xml>        import articles from a pages-articles.xml dump into the cache
+    load                crawl pages into the cache
+    create              build the graph out of the cached pages (fetching any that are missing)
+    similar <url>       list the cached pages with the most words in common with a page, linked or not
+    duplicates          list pairs of cached pages that are near duplicates
+    landmarks           precompute distances to and from landmark pages for faster path finding, and
+                        report how much faster paths are found with them
+    rank                compute the PageRank of every page in the graph and list the highest
+    related <url>       list the pages most related to a page by PageRank personalized to it (may be given
+                        more than once, to find pages related to all of them)
+    hits                compute the HITS hub and authority scores of every page and list the highest of each
+    components          report the sizes of the graph's strongly and weakly connected components and list
+                        the pages that can't be reached from the seeds
+    serve               serve the graph over http
+    help                print this message
+
+options:
+    --config <file>     read options from a file of `key = value` lines (keys are the option names below)
+    --seed <url>        a page to start from, e.g. /wiki/Astronomy (may be given more than once)
+    --max-pages <n>     the number of pages to crawl / put in the graph
+    --max-depth <n>     don't follow links more than n clicks away from a seed
+    --threads <n>       the number of crawler threads
+    --base-url <url>    the wiki to crawl, e.g. https://en.wikipedia.org
+    --mirror <dir>      read pages from a directory of saved html files instead of the web
+    --data-dir <dir>    where the cache and graph are kept
+    --listen <addr>     the address to serve on, e.g. localhost:1243
+    --pagelinks <sql>   take links from a pagelinks.sql dump when importing
+    --resume            resume an interrupted crawl (load only)
+    --user-agent <ua>   the User-Agent to send
+    --rate <n>          the most requests per second to make to a host
+    --burst <n>         how many requests may be made to a host at once before rate limiting kicks in
+    --max-retries <n>   how many times to retry a request that failed with a 429, 5xx or timeout
+    --timeout <secs>    how long to wait for a response
+    --ignore-robots     fetch pages even if robots.txt disallows them
+    --skip-disambiguation
+                        leave disambiguation pages (and the links on them) out of the graph
+    --metric <name>     how similar linked pages are, which decides edge weights (1.1 - similarity):
+                          cosine      cosine of word counts (the default)
+                          jaccard     share of words in common
+                          tfidf       cosine of tf-idf vectors (indexes the cache)
+                          bm25        normalized BM25 (indexes the cache)
+                          cocitation  share of pages linking to both
+                          uniform     every edge weighs 1, so paths are measured in clicks
+                          anchor      overlap of the link's text with the title of the page it leads to
+    --similar-count <n> how many pages `similar` lists (default 10)
+    --duplicate-threshold <x>
+                        how much of their words (0 to 1) two pages must share to be near duplicates (default 0.9)
+    --landmarks <n>     how many landmarks `landmarks` picks (default 16)
+    --landmark-selection <how>
+                        how landmarks are picked: farthest (each as far as possible from the others, the
+                        default) or degree (the pages with the most links)
+    --damping <x>       how likely PageRank's surfer is to follow a link rather than jump (default 0.85)
+    --tolerance <x>     how little PageRank scores have to change by to stop iterating (default 1e-10)
+    --top <n>           how many pages `rank`, `related` and `hits` list (default 20)
+    --lowercase <bool>  lowercase words before counting them (default true)
+    --stop-words <bool> drop common english words like `the` (default true)
+    --min-word-len <n>  drop words shorter than this (default 2)
+    --stem <language>   stem words with the snowball stemmer for a language, e.g. english (default none)
+                        The tokenizer settings are saved with the cache; run clean before changing them.
+    --content-root <class>
+                        only take text and links from inside the element with this class (default
+                        mw-parser-output, `none` for the whole page)
+    --exclude-class <class>
+                        ignore text and links inside elements with this class, on top of the defaults
+                        (navbox, reflist, hatnote, ...)
+    --include-class <class>
+                        stop ignoring elements with this class
+    --exclude-section <heading>
+                        ignore text and links in sections with this heading, on top of the defaults
+                        (See also, References, External links, ...)
+    --include-section <heading>
+                        stop ignoring sections with this heading
+";
+
+/// The commands main knows how to run. When several are given they always run in this order.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub enum Command {
+    Help,
+    Clean,
+    Import(String),
+    Create,
+    Load,
+    Similar(String),
+    Duplicates,
+    Landmarks,
+    Rank,
+    Related(Vec<String>),
+    Hits,
+    Components,
+    Serve
+}
+
+/// Everything that can be configured about a run, from the command line and/or a config file.
+pub struct Config {
+    pub seeds: Vec<String>,
+    pub max_pages: i32,
+    /// The furthest (in clicks) from a seed to crawl, or None to crawl until max_pages.
+    pub max_depth: Option<u64>,
+    pub threads: i32,
+    pub base_url: String,
+    pub mirror: Option<String>,
+    pub data_dir: String,
+    pub listen: String,
+    pub pagelinks: Option<String>,
+    pub resume: bool,
+    pub skip_disambiguation: bool,
+    /// Which parts of a page's html text and links are taken from.
+    pub content: ContentFilter,
+    /// How page text is split into words.
+    pub tokenizer: TokenizerConfig,
+    /// The name of the similarity metric edges are weighted with, one of similarity::METRICS
+    pub metric: String,
+    /// How many pages `similar` lists.
+    pub similar_count: usize,
+    /// The estimated Jaccard similarity at which `duplicates` considers two pages near duplicates.
+    pub duplicate_threshold: f64,
+    /// How many landmarks to precompute distances for.
+    pub landmarks: usize,
+    /// How landmarks are picked, one of landmarks::SELECTIONS
+    pub landmark_selection: String,
+    /// The chance PageRank's surfer follows a link rather than jumping.
+    pub damping: f64,
+    /// PageRank stops iterating once the scores change by less than this in total.
+    pub tolerance: f64,
+    /// How many pages `rank`, `related` and `hits` list.
+    pub top: usize,
+    pub politeness: Politeness
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn parse_num<T: ::std::str::FromStr>(key: &str, value: &str) -> Result<T, io::Error> {
+    match value.trim().parse::<T>() {
+        Ok(x) => Ok(x),
+        Err(_) => Err(invalid(format!("expected a number for {}, found \"{}\"", key, value)))
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, io::Error> {
+    match value.trim() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(invalid(format!("expected true or false for {}, found \"{}\"", key, value)))
+    }
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            seeds: vec![],
+            max_pages: DEFAULT_MAX_PAGES,
+            max_depth: None,
+            threads: DEFAULT_THREADS,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            mirror: None,
+            data_dir: DEFAULT_DATA_DIR.to_string(),
+            listen: DEFAULT_LISTEN.to_string(),
+            pagelinks: None,
+            resume: false,
+            skip_disambiguation: false,
+            content: ContentFilter::new(),
+            tokenizer: TokenizerConfig::new(),
+            metric: "cosine".to_string(),
+            similar_count: 10,
+            duplicate_threshold: 0.9,
+            landmarks: 16,
+            landmark_selection: "farthest".to_string(),
+            damping: 0.85,
+            tolerance: 1e-10,
+            top: 20,
+            politeness: Politeness::new()
+        }
+    }
+
+    /// The path of a file in the data directory.
+    pub fn path(&self, name: &str) -> String {
+        format!("{}/{}", self.data_dir.trim_right_matches('/'), name)
+    }
+
+    /// The pages to begin crawling from, normalized the same way links in pages are.
+    pub fn seeds(&self) -> Vec<String> {
+        if self.seeds.is_empty() {
+            vec![DEFAULT_SEED.to_string()]
+        } else {
+            self.seeds.iter().map(|seed| link::normalize(seed).unwrap_or(seed.clone())).collect()
+        }
+    }
+
+    /// Sets the option key (an option name without the leading dashes) to value.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), io::Error> {
+        let value = value.trim();
+        match key {
+            "seed" => self.seeds.push(value.to_string()),
+            "max-pages" => self.max_pages = try!(parse_num(key, value)),
+            "max-depth" => self.max_depth = Some(try!(parse_num(key, value))),
+            "threads" => self.threads = try!(parse_num(key, value)),
+            "base-url" => self.base_url = value.trim_right_matches('/').to_string(),
+            "mirror" => self.mirror = Some(value.to_string()),
+            "data-dir" => self.data_dir = value.to_string(),
+            "listen" => self.listen = value.to_string(),
+            "pagelinks" => self.pagelinks = Some(value.to_string()),
+            "resume" => self.resume = try!(parse_bool(key, value)),
+            "skip-disambiguation" => self.skip_disambiguation = try!(parse_bool(key, value)),
+            "content-root" => self.content.root_class = match value {
+                "" | "none" => None,
+                class => Some(class.to_string())
+            },
+            "exclude-class" => self.content.excluded_classes.push(value.to_string()),
+            "include-class" => self.content.excluded_classes.retain(|x| x != value),
+            "exclude-section" => self.content.excluded_sections.push(value.to_string()),
+            "include-section" => self.content.excluded_sections.retain(|x| x.to_lowercase() != value.to_lowercase()),
+            "metric" => {
+                if !similarity::METRICS.contains(&value) {
+                    return Err(invalid(format!("unknown metric \"{}\", expected one of {}", value, similarity::METRICS.join(", "))));
+                }
+                self.metric = value.to_string();
+            },
+            "similar-count" => self.similar_count = try!(parse_num(key, value)),
+            "duplicate-threshold" => self.duplicate_threshold = try!(parse_num(key, value)),
+            "landmarks" => self.landmarks = try!(parse_num(key, value)),
+            "damping" => {
+                let damping: f64 = try!(parse_num(key, value));
+                if !(0.0..1.0).contains(&damping) {
+                    return Err(invalid(format!("damping has to be at least 0 and less than 1, found {}", value)));
+                }
+                self.damping = damping;
+            },
+            "tolerance" => self.tolerance = try!(parse_num(key, value)),
+            "top" => self.top = try!(parse_num(key, value)),
+            "landmark-selection" => {
+                if !landmarks::SELECTIONS.contains(&value) {
+                    return Err(invalid(format!("unknown landmark selection \"{}\", expected one of {}", value, landmarks::SELECTIONS.join(", "))));
+                }
+                self.landmark_selection = value.to_string();
+            },
+            "lowercase" => self.tokenizer.lowercase = try!(parse_bool(key, value)),
+            "stop-words" => self.tokenizer.stop_words = try!(parse_bool(key, value)),
+            "min-word-len" => self.tokenizer.min_len = try!(parse_num(key, value)),
+            "stem" => self.tokenizer.stem = match value {
+                "" | "none" => None,
+                language => match tokenizer::stemmer_algorithm(language) {
+                    Some(_) => Some(language.to_lowercase()),
+                    None => return Err(invalid(format!("no stemmer for \"{}\"", language)))
+                }
+            },
+            "user-agent" => self.politeness.user_agent = value.to_string(),
+            "rate" => self.politeness.requests_per_sec = try!(parse_num(key, value)),
+            "burst" => self.politeness.burst = try!(parse_num(key, value)),
+            "max-retries" => self.politeness.max_retries = try!(parse_num(key, value)),
+            "timeout" => self.politeness.timeout = Duration::from_secs(try!(parse_num(key, value))),
+            "ignore-robots" => self.politeness.obey_robots = !try!(parse_bool(key, value)),
+            _ => return Err(invalid(format!("unknown option \"{}\"", key)))
+        }
+        Ok(())
+    }
+
+    /// Reads options from a file of `key = value` lines. Blank lines and lines starting with # are ignored.
+    pub fn read_file(&mut self, path: &str) -> Result<(), io::Error> {
+        let mut file;
+        check!(File::open(path), file);
+        let mut contents = String::new();
+        check!(file.read_to_string(&mut contents));
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+            match line.find('=') {
+                Some(ind) => check!(self.set(line[..ind].trim(), &line[ind + 1..])),
+                None => return Err(invalid(format!("{}:{}: expected `key = value`", path, i + 1)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the command line (without the program name) into the commands to run and the config to run them with.
+    /// Options on the command line take precedence over those in a config file.
+    pub fn from_args(args: Vec<String>) -> Result<(Vec<Command>, Config), io::Error> {
+        let mut config = Config::new();
+        for i in 0..args.len() {
+            if args[i] == "--config" {
+                match args.get(i + 1) {
+                    Some(path) => check!(config.read_file(path)),
+                    None => return Err(invalid("--config requires a file".to_string()))
+                }
+            }
+        }
+
+        let mut commands = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                let _ = args.next();
+            } else if arg == "--resume" || arg == "--ignore-robots" || arg == "--skip-disambiguation" {
+                check!(config.set(&arg[2..], "true"));
+            } else if let Some(key) = arg.strip_prefix("--") {
+                match args.next() {
+                    Some(value) => check!(config.set(key, &value)),
+                    None => return Err(invalid(format!("{} requires a value", arg)))
+                }
+            } else {
+                match arg.as_str() {
+                    "clean" => commands.push(Command::Clean),
+                    "serve" | "server" => commands.push(Command::Serve),
+                    "create" => commands.push(Command::Create),
+                    "load" => commands.push(Command::Load),
+                    "help" => commands.push(Command::Help),
+                    "duplicates" => commands.push(Command::Duplicates),
+                    "landmarks" => commands.push(Command::Landmarks),
+                    "rank" => commands.push(Command::Rank),
+                    "hits" => commands.push(Command::Hits),
+                    "components" => commands.push(Command::Components),
+                    // Every page given to related is a seed of the same query.
+                    "related" => match args.next() {
+                        Some(url) => {
+                            let existing = commands.iter().position(|x| matches!(*x, Command::Related(_)));
+                            match existing {
+                                Some(i) => if let Command::Related(ref mut urls) = commands[i] { urls.push(url) },
+                                None => commands.push(Command::Related(vec![url]))
+                            }
+                        },
+                        None => return Err(invalid("related requires a page, e.g. /wiki/Astronomy".to_string()))
+                    },
+                    "similar" => match args.next() {
+                        Some(url) => commands.push(Command::Similar(url)),
+                        None => return Err(invalid("similar requires a page, e.g. /wiki/Astronomy".to_string()))
+                    },
+                    "import" => match args.next() {
+                        Some(xml) => commands.push(Command::Import(xml)),
+                        None => return Err(invalid("import requires a pages-articles.xml dump".to_string()))
+                    },
+                    // A bare number is the page limit, as it always has been.
+                    _ => match arg.parse::<i32>() {
+                        Ok(n) => config.max_pages = n,
+                        Err(_) => return Err(invalid(format!("unknown command \"{}\"", arg)))
+                    }
+                }
+            }
+        }
+        commands.sort();
+        commands.dedup();
+        Ok((commands, config))
+    }
+}
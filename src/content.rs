@@ -0,0 +1,101 @@
+use select::document::Document;
+use select::node::Node;
+use select::predicate::Class;
+
+/// The class of the element Wikipedia puts the article itself in.
+pub const DEFAULT_CONTENT_ROOT: &'static str = "mw-parser-output";
+
+/// Classes of boilerplate inside the article: navigation boxes, reference lists, hatnotes, maintenance
+/// banners and edit links.
+pub const DEFAULT_EXCLUDED_CLASSES: [&'static str; 17] = [
+    "navbox", "vertical-navbox", "sidebar", "reflist", "references", "refbegin", "reference", "hatnote",
+    "ambox", "metadata", "noprint", "mw-editsection", "toc", "catlinks", "sistersitebox", "portalbox", "side-box"
+];
+
+/// Sections at the end of an article that list other pages rather than talk about the subject.
+pub const DEFAULT_EXCLUDED_SECTIONS: [&'static str; 9] = [
+    "See also", "Notes", "References", "Citations", "Footnotes", "Sources", "Bibliography", "Further reading",
+    "External links"
+];
+
+/// Decides which parts of a page's html are article content. Only text and links in the content are used.
+#[derive(Clone)]
+pub struct ContentFilter {
+    /// The class of the element holding the content, or None to use the whole document. If no element
+    /// has the class the whole document is used.
+    pub root_class: Option<String>,
+
+    /// Elements with any of these classes (and everything in them) aren't content.
+    pub excluded_classes: Vec<String>,
+
+    /// Sections with these headings (and their subsections) aren't content. Compared case insensitively.
+    pub excluded_sections: Vec<String>
+}
+
+impl ContentFilter {
+    pub fn new() -> ContentFilter {
+        ContentFilter {
+            root_class: Some(DEFAULT_CONTENT_ROOT.to_string()),
+            excluded_classes: DEFAULT_EXCLUDED_CLASSES.iter().map(|x| x.to_string()).collect(),
+            excluded_sections: DEFAULT_EXCLUDED_SECTIONS.iter().map(|x| x.to_string()).collect()
+        }
+    }
+
+    /// A filter that treats the whole document as content.
+    pub fn none() -> ContentFilter {
+        ContentFilter {
+            root_class: None,
+            excluded_classes: vec![],
+            excluded_sections: vec![]
+        }
+    }
+
+    /// The index of the content root in document, if there is one.
+    pub fn root(&self, document: &Document) -> Option<usize> {
+        match self.root_class {
+            Some(ref class) => document.find(Class(class.as_str())).next().map(|node| node.index()),
+            None => None
+        }
+    }
+
+    /// Returns true if node has one of the excluded classes.
+    pub fn is_excluded(&self, node: &Node) -> bool {
+        match node.attr("class") {
+            Some(classes) => classes.split_whitespace().any(|class| self.excluded_classes.iter().any(|x| x == class)),
+            None => false
+        }
+    }
+
+    /// Returns true if node is content, i.e. it is inside root (the result of self.root) and neither it nor
+    /// anything between it and root is excluded.
+    pub fn keeps(&self, node: &Node, root: Option<usize>) -> bool {
+        let mut ancestor = Some(*node);
+        while let Some(x) = ancestor {
+            if Some(x.index()) == root { return true }
+            if self.is_excluded(&x) { return false }
+            ancestor = x.parent();
+        }
+        root.is_none()
+    }
+
+    pub fn excludes_section(&self, heading: &str) -> bool {
+        self.excluded_sections.iter().any(|x| x.to_lowercase() == heading.to_lowercase())
+    }
+
+    /// The text of node, leaving out any excluded elements inside it.
+    pub fn text(&self, node: &Node) -> String {
+        let mut text = String::new();
+        self.push_text(node, &mut text);
+        text
+    }
+
+    fn push_text(&self, node: &Node, into: &mut String) {
+        for child in node.children() {
+            if let Some(text) = child.as_text() {
+                into.push_str(text);
+            } else if !self.is_excluded(&child) {
+                self.push_text(&child, into);
+            }
+        }
+    }
+}
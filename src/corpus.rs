@@ -0,0 +1,157 @@
+use frequency_table::FrequencyTable;
+use page::Page;
+use ptree::PTree;
+use std::collections::HashMap;
+use std::io;
+
+/// BM25 term frequency saturation.
+const BM25_K1: f64 = 1.2;
+
+/// BM25 document length normalization.
+const BM25_B: f64 = 0.75;
+
+/// Document frequencies of the terms in a set of pages, used to tell rare (informative) terms from common ones.
+pub struct CorpusIndex {
+    /// The number of pages.
+    pub documents: u64,
+    /// The total number of terms in all of the pages.
+    pub total_len: u64,
+    /// How many pages each term (by term id) appears in.
+    pub df: HashMap<u32, u64>
+}
+
+impl CorpusIndex {
+    pub fn new() -> CorpusIndex {
+        CorpusIndex { documents: 0, total_len: 0, df: HashMap::new() }
+    }
+
+    /// Indexes every page in the cache. Pages that can't be read (e.g. ones in an old format) are skipped.
+    pub fn from_cache(cache: &mut PTree<String, Page>) -> Result<CorpusIndex, io::Error> {
+        let mut index = CorpusIndex::new();
+        let keys;
+        check!(cache.keys(), keys);
+        for url in keys.iter() {
+            if let Ok(Some(page)) = cache.search(url) {
+                index.add(&page.word_freq);
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn add(&mut self, doc: &FrequencyTable) {
+        self.documents += 1;
+        self.total_len += doc.sum() as u64;
+        for &(term, _) in doc.terms().iter() {
+            *self.df.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    fn df(&self, term: u32) -> f64 {
+        *self.df.get(&term).unwrap_or(&0) as f64
+    }
+
+    /// Smoothed inverse document frequency, never less than 1.
+    pub fn idf(&self, term: u32) -> f64 {
+        ((self.documents as f64 + 1.0) / (self.df(term) + 1.0)).ln() + 1.0
+    }
+
+    /// The BM25 flavour of idf, which is close to 0 for terms in most pages.
+    pub fn bm25_idf(&self, term: u32) -> f64 {
+        let n = self.documents as f64;
+        let df = self.df(term);
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    fn avg_len(&self) -> f64 {
+        if self.documents == 0 { 1.0 } else { self.total_len as f64 / self.documents as f64 }
+    }
+
+    /// Cosine similarity of the tf-idf vectors of two pages.
+    pub fn tfidf_cosine(&self, a: &FrequencyTable, b: &FrequencyTable) -> f64 {
+        let norm = |doc: &FrequencyTable| {
+            doc.terms().iter().fold(0.0, |acc, &(term, tf)| {
+                let w = tf as f64 * self.idf(term);
+                acc + w * w
+            }).sqrt()
+        };
+        let mut dot = 0.0;
+        a.join(b, |term, tf, other| {
+            let idf = self.idf(term);
+            dot += (tf as f64 * idf) * (other as f64 * idf);
+        });
+        let denom = norm(a) * norm(b);
+        if denom == 0.0 { 0.0 } else { dot / denom }
+    }
+
+    /// The BM25 score of doc for a query made of the distinct terms of query.
+    pub fn bm25(&self, query: &FrequencyTable, doc: &FrequencyTable) -> f64 {
+        let len_norm = 1.0 - BM25_B + BM25_B * doc.sum() / self.avg_len();
+        let mut score = 0.0;
+        query.join(doc, |term, _, tf| {
+            let tf = tf as f64;
+            score += self.bm25_idf(term) * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * len_norm);
+        });
+        score
+    }
+
+    /// BM25 turned into a symmetric similarity: the scores of each page against the other over the scores of each
+    /// page against itself. Between 0 and 1, the closer to 1 the more similar.
+    pub fn bm25_similarity(&self, a: &FrequencyTable, b: &FrequencyTable) -> f64 {
+        let denom = self.bm25(a, a) + self.bm25(b, b);
+        if denom == 0.0 { return 0.0 }
+        ((self.bm25(a, b) + self.bm25(b, a)) / denom).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(ids: &[u32]) -> FrequencyTable {
+        FrequencyTable::new_from(ids.to_vec())
+    }
+
+    fn index(docs: &[&FrequencyTable]) -> CorpusIndex {
+        let mut index = CorpusIndex::new();
+        for doc in docs.iter() { index.add(doc); }
+        index
+    }
+
+    #[test]
+    fn bm25_matches_the_formula() {
+        let (a, b, c) = (doc(&[1, 1, 2]), doc(&[2, 3]), doc(&[3, 3, 3, 4]));
+        let index = index(&[&a, &b, &c]);
+        assert_eq!(index.documents, 3);
+        assert_eq!(index.total_len, 9);
+        assert_eq!(index.df[&3], 2);
+
+        // a is as long as the average page, so its length doesn't change the score.
+        let idf = (1.0f64 + (3.0 - 1.0 + 0.5) / (1.0 + 0.5)).ln();
+        let expected = idf * 2.0 * (BM25_K1 + 1.0) / (2.0 + BM25_K1);
+        assert!((index.bm25(&doc(&[1]), &a) - expected).abs() < 1e-12);
+        // Repeating a query term doesn't count it twice.
+        assert!((index.bm25(&doc(&[1, 1]), &a) - expected).abs() < 1e-12);
+        assert_eq!(index.bm25(&doc(&[4]), &a), 0.0);
+    }
+
+    #[test]
+    fn bm25_favours_shorter_pages_and_rarer_terms() {
+        let (short, long, other) = (doc(&[1, 2]), doc(&[1, 2, 3, 4, 5, 6]), doc(&[2, 7]));
+        let index = index(&[&short, &long, &other]);
+        assert!(index.bm25(&doc(&[1]), &short) > index.bm25(&doc(&[1]), &long));
+        // Term 2 is in every page, term 1 in two of them.
+        assert!(index.bm25_idf(1) > index.bm25_idf(2));
+        assert!(index.idf(1) > index.idf(2) && index.idf(2) >= 1.0);
+    }
+
+    #[test]
+    fn bm25_similarity_is_symmetric_and_between_0_and_1() {
+        let (a, b, c) = (doc(&[1, 2, 3]), doc(&[1, 2, 4]), doc(&[5, 6]));
+        let index = index(&[&a, &b, &c]);
+        let ab = index.bm25_similarity(&a, &b);
+        assert!(ab > 0.0 && ab < 1.0);
+        assert!((ab - index.bm25_similarity(&b, &a)).abs() < 1e-12);
+        assert!((index.bm25_similarity(&a, &a) - 1.0).abs() < 1e-12);
+        assert_eq!(index.bm25_similarity(&a, &c), 0.0);
+    }
+}
@@ -0,0 +1,93 @@
+use random_access_file::Serialize;
+use cfile_rs::CFile;
+use std::io;
+use std::io::{ Read, Write };
+
+/// A checkpoint of the crawler's progress, enough (together with the visited and link trees) to
+/// resume an interrupted crawl.
+pub struct CrawlState {
+    /// The index in the link tree of the next url in the frontier.
+    pub set_ind: u64,
+
+    /// The number of urls that have been pushed into the link tree.
+    pub set_max: u64,
+
+    /// The number of pages downloaded so far.
+    pub downloaded: u64,
+
+    /// The number of pages visited so far (downloaded or found in the cache).
+    pub visited: u64,
+
+    /// Urls that had been taken off of the frontier but not yet visited when the checkpoint was made.
+    pub in_flight: Vec<String>
+}
+
+impl CrawlState {
+    pub fn new() -> CrawlState {
+        CrawlState { set_ind: 0, set_max: 0, downloaded: 0, visited: 0, in_flight: vec![] }
+    }
+
+    pub fn persist(&self, to: &str) -> Result<(), io::Error> {
+        let mut file;
+        check!(CFile::open(to, "w+"), file);
+        check!(self.serialize(&mut file));
+        check!(file.flush());
+        Ok(())
+    }
+
+    pub fn from_disk(from: &str) -> Result<CrawlState, io::Error> {
+        let mut file;
+        check!(CFile::open(from, "r+"), file);
+        CrawlState::deserialize(&mut file)
+    }
+}
+
+impl Serialize for CrawlState {
+    type DeserializeOutput = CrawlState;
+
+    fn deserialize(from: &mut Read) -> Result<CrawlState, io::Error> {
+        let set_ind;
+        check!(u64::deserialize(from), set_ind);
+        let set_max;
+        check!(u64::deserialize(from), set_max);
+        let downloaded;
+        check!(u64::deserialize(from), downloaded);
+        let visited;
+        check!(u64::deserialize(from), visited);
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut in_flight = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let url;
+            check!(String::deserialize(from), url);
+            in_flight.push(url);
+        }
+        Ok(CrawlState {
+            set_ind: set_ind,
+            set_max: set_max,
+            downloaded: downloaded,
+            visited: visited,
+            in_flight: in_flight
+        })
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.set_ind.serialize(to));
+        check!(self.set_max.serialize(to));
+        check!(self.downloaded.serialize(to));
+        check!(self.visited.serialize(to));
+        check!((self.in_flight.len() as u64).serialize(to));
+        for url in self.in_flight.iter() {
+            check!(url.serialize(to));
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self) -> u64 {
+        let mut len = 8 * 5;
+        for url in self.in_flight.iter() {
+            len += url.serialized_len();
+        }
+        len
+    }
+}
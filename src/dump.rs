@@ -0,0 +1,564 @@
+use regex::{ Regex, Captures };
+use alias::AliasTable;
+use link;
+use link::{ title_to_url, Link, LinkRegion };
+use page::Page;
+use ptree::PTree;
+use tokenizer::Tokenizer;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{ BufRead, BufReader };
+
+lazy_static! {
+    static ref WIKI_LINK_REGEX: Regex = Regex::new(r#"\[\[([^\[\]|]+)(?:\|([^\[\]]*))?\]\]"#).unwrap();
+    static ref EXTERNAL_LINK_REGEX: Regex = Regex::new(r#"\[https?://[^ \]]*( ([^\]]*))?\]"#).unwrap();
+    static ref REF_REGEX: Regex = Regex::new(r#"(?s)<ref[^>/]*/>|<ref[^>]*>.*?</ref>"#).unwrap();
+    static ref COMMENT_REGEX: Regex = Regex::new(r#"(?s)<!--.*?-->"#).unwrap();
+    static ref TAG_REGEX: Regex = Regex::new(r#"<[^>]*>"#).unwrap();
+    static ref HEADING_REGEX: Regex = Regex::new(r#"(?m)^(={2,4})\s*(.+?)\s*={2,4}\s*$"#).unwrap();
+    static ref DISAMBIGUATION_REGEX: Regex = Regex::new(r#"(?i)\{\{\s*(disambiguation|disambig|dab|hndis|geodis|set index article)\s*[|}]"#).unwrap();
+}
+
+/// Interwiki prefixes that lead off to sister projects. Links with these are dropped.
+const INTERWIKI_PREFIXES: [&'static str; 3] = ["wiktionary", "wikt", "commons"];
+
+/// The language codes of Wikipedias, which as prefixes make interlanguage links like [[fr:Astronomie]].
+const LANGUAGE_CODES: &'static [&'static str] = &[
+    "aa", "ab", "ace", "ady", "af", "ak", "als", "alt", "am", "an", "ang", "ar", "arc", "ary", "arz", "as", "ast",
+    "atj", "av", "avk", "awa", "ay", "az", "azb", "ba", "ban", "bar", "bat-smg", "bcl", "be", "be-tarask",
+    "be-x-old", "bg", "bh", "bi", "bjn", "bm", "bn", "bo", "bpy", "br", "bs", "bug", "bxr", "ca", "cbk-zam", "cdo",
+    "ce", "ceb", "ch", "cho", "chr", "chy", "ckb", "co", "cr", "crh", "cs", "csb", "cu", "cv", "cy", "da", "de",
+    "din", "diq", "dsb", "dty", "dv", "dz", "ee", "el", "eml", "en", "eo", "es", "et", "eu", "ext", "fa", "ff", "fi",
+    "fiu-vro", "fj", "fo", "fr", "frp", "frr", "fur", "fy", "ga", "gag", "gan", "gcr", "gd", "gl", "glk", "gn",
+    "gom", "gor", "got", "gu", "gv", "ha", "hak", "haw", "he", "hi", "hif", "ho", "hr", "hsb", "ht", "hu", "hy",
+    "hyw", "hz", "ia", "id", "ie", "ig", "ii", "ik", "ilo", "inh", "io", "is", "it", "iu", "ja", "jam", "jbo", "jv",
+    "ka", "kaa", "kab", "kbd", "kbp", "kg", "ki", "kj", "kk", "kl", "km", "kn", "ko", "koi", "kr", "krc", "ks",
+    "ksh", "ku", "kv", "kw", "ky", "la", "lad", "lb", "lbe", "lez", "lfn", "lg", "li", "lij", "lld", "lmo", "ln",
+    "lo", "lrc", "lt", "ltg", "lv", "mad", "mai", "map-bms", "mdf", "mg", "mh", "mhr", "mi", "min", "mk", "ml", "mn",
+    "mni", "mnw", "mo", "mr", "mrj", "ms", "mt", "mus", "mwl", "my", "myv", "mzn", "na", "nah", "nap", "nds",
+    "nds-nl", "ne", "new", "ng", "nia", "nl", "nn", "no", "nov", "nqo", "nrm", "nso", "nv", "ny", "oc", "olo", "om",
+    "or", "os", "pa", "pag", "pam", "pap", "pcd", "pdc", "pfl", "pi", "pih", "pl", "pms", "pnb", "pnt", "ps", "pt",
+    "qu", "rm", "rmy", "rn", "ro", "roa-rup", "roa-tara", "ru", "rue", "rw", "sa", "sah", "sat", "sc", "scn", "sco",
+    "sd", "se", "sg", "sh", "shi", "shn", "si", "simple", "sk", "skr", "sl", "sm", "smn", "sn", "so", "sq", "sr",
+    "srn", "ss", "st", "stq", "su", "sv", "sw", "szl", "szy", "ta", "tay", "tcy", "te", "tet", "tg", "th", "ti",
+    "tk", "tl", "tn", "to", "tpi", "tr", "trv", "ts", "tt", "tum", "tw", "ty", "tyv", "udm", "ug", "uk", "ur", "uz",
+    "ve", "vec", "vep", "vi", "vls", "vo", "wa", "war", "wo", "wuu", "xal", "xh", "xmf", "yi", "yo", "za", "zea",
+    "zh", "zh-classical", "zh-min-nan", "zh-yue", "zu"
+];
+
+/// A single <page> out of a pages-articles.xml dump.
+pub struct DumpPage {
+    pub id: u64,
+    pub title: String,
+    pub namespace: i64,
+    /// The title this page redirects to, if it is a redirect.
+    pub redirect: Option<String>,
+    /// The raw wikitext of the latest revision.
+    pub text: String
+}
+
+impl DumpPage {
+    /// The url this page would have on the live site, e.g. "/wiki/Solar_System"
+    pub fn url(&self) -> String {
+        title_to_url(&self.title)
+    }
+
+    /// Converts this page into a Page, extracting paragraph text, article links and metadata from the wikitext.
+    pub fn to_page(&self, tokenizer: &Tokenizer) -> Page {
+        let mut page = Page::from_parts(&self.url(), &plain_text(&self.text), wiki_links(&self.text), tokenizer);
+        page.title = self.title.clone();
+        let lead = match HEADING_REGEX.find(&self.text) {
+            Some(heading) => &self.text[..heading.start()],
+            None => &self.text[..]
+        };
+        page.summary = plain_text(lead).split_whitespace().collect::<Vec<_>>().join(" ");
+        page.categories = categories(&self.text);
+        page.headings = HEADING_REGEX.captures_iter(&self.text).map(|cap| inline_text(&cap[2])).collect();
+        page.infobox = infobox(&self.text);
+        page.disambiguation = DISAMBIGUATION_REGEX.is_match(&self.text) || self.title.ends_with("(disambiguation)");
+        page
+    }
+}
+
+/// Streams pages out of a (decompressed) pages-articles.xml dump without loading the whole thing into memory.
+pub struct DumpReader<R: BufRead> {
+    read: R,
+    line: String
+}
+
+impl<R: BufRead> DumpReader<R> {
+    pub fn new(read: R) -> DumpReader<R> {
+        DumpReader { read: read, line: String::new() }
+    }
+
+    /// Reads the next line into self.line, returns false at the end of the file.
+    fn next_line(&mut self) -> Result<bool, io::Error> {
+        self.line.clear();
+        let len;
+        check!(self.read.read_line(&mut self.line), len);
+        Ok(len != 0)
+    }
+
+    /// Returns the next page in the dump, or None once the dump has been exhausted.
+    pub fn next_page(&mut self) -> Result<Option<DumpPage>, io::Error> {
+        // Skip ahead to the start of the next page
+        loop {
+            let more;
+            check!(self.next_line(), more);
+            if !more { return Ok(None) }
+            if self.line.trim() == "<page>" { break }
+        }
+
+        let mut page = DumpPage { id: 0, title: String::new(), namespace: 0, redirect: None, text: String::new() };
+        let mut seen_id = false;
+        loop {
+            let more;
+            check!(self.next_line(), more);
+            if !more {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "dump ended in the middle of a page"));
+            }
+            let line = self.line.trim().to_string();
+            if line == "</page>" {
+                return Ok(Some(page));
+            } else if let Some(title) = tag_value(&line, "title") {
+                page.title = title;
+            } else if let Some(ns) = tag_value(&line, "ns") {
+                page.namespace = ns.parse().unwrap_or(0);
+            } else if let Some(id) = tag_value(&line, "id") {
+                // Revisions and contributors have ids too, only the first one belongs to the page.
+                if !seen_id {
+                    page.id = id.parse().unwrap_or(0);
+                    seen_id = true;
+                }
+            } else if line.starts_with("<redirect") {
+                if let Some(start) = line.find("title=\"") {
+                    let rest = &line[start + 7..];
+                    if let Some(end) = rest.find('"') {
+                        page.redirect = Some(unescape(&rest[..end]));
+                    }
+                }
+            } else if line.starts_with("<text") {
+                check!(self.read_text(&mut page.text));
+            }
+        }
+    }
+
+    /// Reads the contents of a <text> element, which starts on the current line and may span many more.
+    fn read_text(&mut self, into: &mut String) -> Result<(), io::Error> {
+        let start = match self.line.find("<text") {
+            Some(x) => x,
+            None => return Ok(())
+        };
+        let open_end = match self.line[start..].find('>') {
+            Some(x) => start + x,
+            None => return Ok(())
+        };
+        // <text ... /> is an empty page
+        if self.line[..open_end].ends_with('/') { return Ok(()) }
+
+        let mut text = self.line[open_end + 1..].to_string();
+        while !text.contains("</text>") {
+            let more;
+            check!(self.next_line(), more);
+            if !more { break }
+            text.push_str(&self.line);
+        }
+        if let Some(end) = text.find("</text>") {
+            text.truncate(end);
+        }
+        *into = unescape(&text);
+        Ok(())
+    }
+}
+
+/// If line is exactly <tag>value</tag>, returns the unescaped value.
+fn tag_value(line: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    if line.starts_with(&open) && line.ends_with(&close) && line.len() >= open.len() + close.len() {
+        Some(unescape(&line[open.len()..line.len() - close.len()]))
+    } else {
+        None
+    }
+}
+
+/// Undoes the xml escaping used in the dump.
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+     .replace("&gt;", ">")
+     .replace("&quot;", "\"")
+     .replace("&#039;", "'")
+     .replace("&apos;", "'")
+     .replace("&amp;", "&")
+}
+
+/// Returns true if the title points outside of the article namespace, e.g. "File:Sun.png" or an interwiki link.
+fn is_ignored_title(title: &str) -> bool {
+    if !link::is_article_title(title) { return true }
+    match title.find(':') {
+        Some(ind) => {
+            let prefix = title[..ind].trim().to_lowercase();
+            INTERWIKI_PREFIXES.contains(&prefix.as_str()) || LANGUAGE_CODES.contains(&prefix.as_str())
+        },
+        None => false
+    }
+}
+
+/// Finds all article links in some wikitext, in the order they appear. Links before the first heading are
+/// in the lead, and links inside the infobox template are in the infobox.
+pub fn wiki_links(wikitext: &str) -> Vec<Link> {
+    let lead_end = HEADING_REGEX.find(wikitext).map_or(wikitext.len(), |x| x.start());
+    let infobox = infobox_span(wikitext).unwrap_or((0, 0));
+    let mut links = vec![];
+    for cap in WIKI_LINK_REGEX.captures_iter(wikitext) {
+        let mut target = cap[1].trim();
+        if target.starts_with(':') { target = &target[1..]; }
+        if let Some(ind) = target.find('#') { target = &target[..ind]; }
+        if target.is_empty() || is_ignored_title(target) { continue }
+        let start = cap.get(0).unwrap().start();
+        let region = if start >= infobox.0 && start < infobox.1 {
+            LinkRegion::Infobox
+        } else if start < lead_end {
+            LinkRegion::Lead
+        } else {
+            LinkRegion::Body
+        };
+        let anchor = inline_text(cap.get(2).map_or(target, |x| x.as_str()));
+        links.push(Link::new(title_to_url(target), &anchor, region));
+    }
+    links
+}
+
+/// Finds the names of the categories some wikitext puts its page in, e.g. "Planets" for [[Category:Planets|Earth]]
+fn categories(wikitext: &str) -> Vec<String> {
+    let mut categories = vec![];
+    for cap in WIKI_LINK_REGEX.captures_iter(wikitext) {
+        let target = cap[1].trim();
+        if let Some(ind) = target.find(':') {
+            if target[..ind].trim().eq_ignore_ascii_case("category") {
+                categories.push(target[ind + 1..].trim().to_string());
+            }
+        }
+    }
+    categories
+}
+
+/// Finds where the first {{Infobox ...}} template in some wikitext starts and ends (just past its closing braces).
+fn infobox_span(wikitext: &str) -> Option<(usize, usize)> {
+    // to_lowercase can change byte lengths, so match case insensitively by hand
+    let start = match wikitext.char_indices().map(|(i, _)| i).find(|&i| {
+        wikitext[i..].len() >= 9 && wikitext.is_char_boundary(i + 9) && wikitext[i..i + 9].eq_ignore_ascii_case("{{infobox")
+    }) {
+        Some(x) => x,
+        None => return None
+    };
+    let mut depth = 0;
+    let mut pos = start;
+    while pos < wikitext.len() {
+        let rest = &wikitext[pos..];
+        if rest.starts_with("{{") {
+            depth += 1;
+            pos += 2;
+        } else if rest.starts_with("}}") {
+            depth -= 1;
+            pos += 2;
+            if depth == 0 { return Some((start, pos)) }
+        } else {
+            pos += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    Some((start, wikitext.len()))
+}
+
+/// Extracts the `| label = value` parameters of the first {{Infobox ...}} template in some wikitext.
+fn infobox(wikitext: &str) -> Vec<(String, String)> {
+    let mut rows = vec![];
+    let (start, end) = match infobox_span(wikitext) {
+        Some(x) => x,
+        None => return rows
+    };
+    let body = wikitext[start + 2..end].trim_right_matches('}');
+    // Split the template into its parameters on the |s that aren't inside a nested template or link.
+    let mut params = vec![];
+    let mut param = String::new();
+    let mut depth = 0;
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.starts_with("{{") || rest.starts_with("[[") {
+            depth += 1;
+            param.push_str(&rest[..2]);
+            rest = &rest[2..];
+        } else if depth > 0 && (rest.starts_with("}}") || rest.starts_with("]]")) {
+            depth -= 1;
+            param.push_str(&rest[..2]);
+            rest = &rest[2..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            if c == '|' && depth == 0 {
+                params.push(param);
+                param = String::new();
+            } else {
+                param.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    params.push(param);
+    // The first "parameter" is the template name.
+    for param in params.iter().skip(1) {
+        if let Some(ind) = param.find('=') {
+            let label = param[..ind].trim().replace('_', " ");
+            let value = REF_REGEX.replace_all(&param[ind + 1..], "");
+            let value = inline_text(&strip_nested(&value, "{{", "}}"));
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !label.is_empty() && !value.is_empty() {
+                rows.push((label, value));
+            }
+        }
+    }
+    rows
+}
+
+/// Removes everything between (possibly nested) open and close delimiters, e.g. templates {{ ... }}
+fn strip_nested(text: &str, open: &str, close: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut depth = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.starts_with(open) {
+            depth += 1;
+            rest = &rest[open.len()..];
+        } else if depth > 0 && rest.starts_with(close) {
+            depth -= 1;
+            rest = &rest[close.len()..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            if depth == 0 { result.push(c); }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    result
+}
+
+/// Renders the inline markup of a line of wikitext as plain text: links become their labels,
+/// and tags and bold / italic quotes are dropped.
+fn inline_text(line: &str) -> String {
+    let line = WIKI_LINK_REGEX.replace_all(line, |cap: &Captures| {
+        if is_ignored_title(&cap[1]) {
+            String::new()
+        } else {
+            match cap.get(2) {
+                Some(label) => label.as_str().to_string(),
+                None => cap[1].to_string()
+            }
+        }
+    });
+    let line = EXTERNAL_LINK_REGEX.replace_all(&line, "$2");
+    let line = TAG_REGEX.replace_all(&line, "");
+    line.replace("'''", "").replace("''", "")
+}
+
+/// Extracts the prose paragraphs out of some wikitext, dropping templates, tables, references,
+/// headings, lists and markup. This is the dump equivalent of collecting every <p> in the html.
+pub fn plain_text(wikitext: &str) -> String {
+    let text = COMMENT_REGEX.replace_all(wikitext, "");
+    let text = REF_REGEX.replace_all(&text, "");
+    let text = strip_nested(&text, "{{", "}}");
+    let text = strip_nested(&text, "{|", "|}");
+
+    let mut corpus = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() { continue }
+        match line.chars().next().unwrap() {
+            '=' | '*' | '#' | ':' | ';' | '|' | '!' | '{' | '}' => continue,
+            _ => {}
+        }
+        corpus += &inline_text(line);
+        corpus.push(' ');
+    }
+    corpus
+}
+
+/// Splits the VALUES of a mysqldump INSERT statement into rows of fields, with quoted strings unescaped.
+fn sql_rows(line: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let start = match line.find(" VALUES ") {
+        Some(x) => x + 8,
+        None => return rows
+    };
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut in_row = false;
+    let mut in_string = false;
+    let mut chars = line[start..].chars();
+    while let Some(c) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => if let Some(escaped) = chars.next() { field.push(escaped) },
+                '\'' => in_string = false,
+                _ => field.push(c)
+            }
+        } else {
+            match c {
+                '(' if !in_row => in_row = true,
+                '\'' if in_row => in_string = true,
+                ',' if in_row => row.push(::std::mem::take(&mut field)),
+                ')' if in_row => {
+                    row.push(::std::mem::take(&mut field));
+                    rows.push(::std::mem::take(&mut row));
+                    in_row = false;
+                },
+                _ if in_row => field.push(c),
+                _ => {}
+            }
+        }
+    }
+    rows
+}
+
+/// Reads the links out of a pagelinks.sql dump for the pages in wanted (page id -> url).
+/// Only the classic (pl_from, pl_namespace, pl_title, pl_from_namespace) layout is understood.
+fn read_pagelinks(path: &str, wanted: &HashMap<u64, String>) -> Result<HashMap<u64, Vec<Link>>, io::Error> {
+    let file;
+    check!(File::open(path), file);
+    let mut read = BufReader::new(file);
+    let mut links: HashMap<u64, Vec<Link>> = HashMap::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let len;
+        check!(read.read_line(&mut line), len);
+        if len == 0 { break }
+        if !line.starts_with("INSERT INTO") { continue }
+        for row in sql_rows(&line) {
+            if row.len() < 3 || row[1] != "0" { continue }
+            let from = match row[0].parse::<u64>() {
+                Ok(x) => x,
+                Err(_) => continue
+            };
+            if wanted.contains_key(&from) {
+                // pagelinks.sql doesn't say where on the page a link is or what it says.
+                links.entry(from).or_insert(vec![]).push(Link::new(title_to_url(&row[2]), "", LinkRegion::Body));
+            }
+        }
+    }
+    Ok(links)
+}
+
+/// Imports up to limit articles from a pages-articles.xml dump into the page cache at cache_path.
+/// Redirects are recorded in the alias table at alias_path, and the tokenizer's terms are saved to terms_path
+/// before any page that uses them is cached.
+/// If pagelinks is given, links are taken from that pagelinks.sql dump rather than from the wikitext.
+/// Returns the number of pages imported.
+pub fn import(xml: &str, pagelinks: Option<&str>, cache_path: &str, alias_path: &str, terms_path: &str, limit: usize, tokenizer: &Tokenizer)
+    -> Result<usize, io::Error> {
+    let mut cache = match PTree::<String, Page>::open(cache_path) {
+        Ok(x) => x,
+        Err(_) => {
+            log!("Info", "No cache found at \"{}\", creating a new one.", cache_path);
+            let cache;
+            check!(PTree::<String, Page>::new(cache_path), cache);
+            cache
+        }
+    };
+    let mut aliases;
+    check!(AliasTable::open(alias_path), aliases);
+    let file;
+    check!(File::open(xml), file);
+    let mut reader = DumpReader::new(BufReader::new(file));
+
+    // When the links come from pagelinks.sql the pages have to be held on to until the links are known.
+    let mut pending: HashMap<u64, Page> = HashMap::new();
+    let mut count = 0;
+    while count < limit {
+        let dump_page;
+        check!(reader.next_page(), dump_page);
+        let dump_page = match dump_page {
+            Some(x) => x,
+            None => break
+        };
+        if dump_page.namespace != 0 { continue }
+        if let Some(ref target) = dump_page.redirect {
+            // Redirects to a section, e.g. [[United States#History]], are redirects to the page.
+            let target = target.split('#').next().unwrap().trim();
+            if !target.is_empty() {
+                check!(aliases.insert(&dump_page.url(), &title_to_url(target)));
+            }
+            continue;
+        }
+        let url = dump_page.url();
+        // Entries from an older cache format don't deserialize, and get replaced.
+        if let Ok(Some(_)) = cache.search(&url) { continue }
+
+        let page = dump_page.to_page(tokenizer);
+        if pagelinks.is_some() {
+            pending.insert(dump_page.id, page);
+        } else {
+            check!(tokenizer.terms().persist(terms_path));
+            check!(cache.insert_or_replace(&url, &page));
+        }
+        count += 1;
+        if count % 1000 == 0 {
+            log!("Info", "Imported {} pages...", count);
+        }
+    }
+
+    if let Some(sql) = pagelinks {
+        log!("Info", "Reading links from \"{}\"...", sql);
+        let wanted = pending.iter().map(|(id, page)| (*id, page.url.clone())).collect::<HashMap<u64, String>>();
+        let mut links;
+        check!(read_pagelinks(sql, &wanted), links);
+        check!(tokenizer.terms().persist(terms_path));
+        for (id, mut page) in pending.into_iter() {
+            page.links = link::dedup(links.remove(&id).unwrap_or(vec![]));
+            check!(cache.insert_or_replace(&page.url, &page));
+        }
+    }
+    check!(tokenizer.terms().persist(terms_path));
+    check!(cache.flush());
+    check!(aliases.flush());
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(wikitext: &str) -> Vec<String> {
+        wiki_links(wikitext).into_iter().map(|x| x.url).collect()
+    }
+
+    #[test]
+    fn wiki_links_finds_articles_and_skips_other_namespaces() {
+        let text = "A [[star]] is a [[Plasma (physics)|plasma]] ball, see [[Sun#Structure|the Sun]] and [[:Moon]]. \
+                    [[File:Sun.png|thumb]] [[Category:Stars]] [[wikt:star]] [[fr:Étoile]] [[simple:Star]] \
+                    [[Star Wars: Episode I]] [[#Formation]]";
+        assert_eq!(urls(text), vec!["/wiki/Star", "/wiki/Plasma_(physics)", "/wiki/Sun", "/wiki/Moon",
+                                    "/wiki/Star_Wars:_Episode_I"]);
+    }
+
+    #[test]
+    fn wiki_links_records_regions_and_anchors() {
+        let text = "{{Infobox star\n| type = [[Red dwarf]]\n}}\n[[Sun|Our star]] is close.\n== Formation ==\n[[Nebula]]";
+        let links = wiki_links(text);
+        let regions = links.iter().map(|x| (x.url.as_str(), x.region)).collect::<Vec<_>>();
+        assert_eq!(regions, vec![("/wiki/Red_dwarf", LinkRegion::Infobox), ("/wiki/Sun", LinkRegion::Lead),
+                                 ("/wiki/Nebula", LinkRegion::Body)]);
+        assert_eq!(links[1].anchors, vec!["Our star"]);
+    }
+
+    #[test]
+    fn sql_rows_splits_values_and_unescapes_strings() {
+        let line = "INSERT INTO `pagelinks` VALUES (12,0,'Sun',0),(12,0,'O\\'Brien_(crater)',0),(13,14,'Stars',0);";
+        assert_eq!(sql_rows(line), vec![
+            vec!["12", "0", "Sun", "0"],
+            vec!["12", "0", "O'Brien_(crater)", "0"],
+            vec!["13", "14", "Stars", "0"]
+        ]);
+        // Parentheses and commas inside strings aren't delimiters.
+        assert_eq!(sql_rows("INSERT INTO `pagelinks` VALUES (1,0,'A,(b)',0);"), vec![vec!["1", "0", "A,(b)", "0"]]);
+        assert!(sql_rows("CREATE TABLE `pagelinks` (").is_empty());
+    }
+}
@@ -1,10 +1,7 @@
-use std::io::{ Seek, SeekFrom, Read, Write };
+use std::io::{ Read, Write };
 use std::io;
 use random_access_file::Serialize;
-use cfile_rs::CFile;
 use std::f64;
-use random_access_file;
-use check;
 
 /// Simple edge struct
 pub struct Edge {
@@ -1,69 +1,152 @@
-use lazy_static;
+use random_access_file::Serialize;
 use std::collections::HashMap;
-use page::Page;
+use std::io;
+use std::io::{ Read, Write };
+use tokenizer::Tokenizer;
 
-pub struct FrequencyTable(pub HashMap<String, usize>);
+/// How often each term appears in a page, as a sparse vector of (term id, count) sorted by term id.
+/// Term ids come from the TermDictionary of the Tokenizer that made the table.
+/// The sum and magnitude of the counts are computed once, when the table is made.
+pub struct FrequencyTable {
+    terms: Vec<(u32, f32)>,
+    sum: f64,
+    norm: f64
+}
 
 impl FrequencyTable {
     pub fn new() -> Self {
-        FrequencyTable(HashMap::new())
+        FrequencyTable::from_sorted(vec![])
     }
 
-    pub fn new_from(s: HashMap<String, usize>) -> Self {
-        FrequencyTable(s)
+    /// Makes a table out of (term id, count) pairs that are already sorted by term id.
+    fn from_sorted(terms: Vec<(u32, f32)>) -> Self {
+        let mut sum = 0.0;
+        let mut squares = 0.0;
+        for &(_, freq) in terms.iter() {
+            let freq = freq as f64;
+            sum += freq;
+            squares += freq * freq;
+        }
+        FrequencyTable { terms: terms, sum: sum, norm: squares.sqrt() }
     }
 
-    pub fn new_from_str(s: &str) -> Self {
-        let mut this = FrequencyTable::new();
-        for word_str in s.split_whitespace() {
-            let word = word_str.to_string();
-            this.inc(word);
+    /// Counts the term ids in ids.
+    pub fn new_from(mut ids: Vec<u32>) -> Self {
+        ids.sort();
+        let mut terms: Vec<(u32, f32)> = vec![];
+        for id in ids.into_iter() {
+            match terms.last_mut() {
+                Some(&mut (last, ref mut count)) if last == id => { *count += 1.0; continue },
+                _ => {}
+            }
+            terms.push((id, 1.0));
         }
-        this
+        FrequencyTable::from_sorted(terms)
+    }
+
+    pub fn new_from_str(s: &str, tokenizer: &Tokenizer) -> Self {
+        FrequencyTable::new_from(tokenizer.term_ids(s))
     }
 
-    pub fn inc(&mut self, key: String) {
-        let x = self.0.entry(key).or_insert(0);
-        *x += 1;
+    /// The (term id, count) pairs, sorted by term id.
+    pub fn terms(&self) -> &[(u32, f32)] { &self.terms }
+
+    /// The count of the term with id term.
+    pub fn get(&self, term: u32) -> Option<f32> {
+        match self.terms.binary_search_by_key(&term, |&(id, _)| id) {
+            Ok(ind) => Some(self.terms[ind].1),
+            Err(_) => None
+        }
     }
 
-    /// Compares two FrequencyTables and returns a f64 which represents their
-    /// difference. The closer to 1, the more similar.
-    /// The algorithm used is a spin off of cosine similarity.
+    pub fn len(&self) -> usize { self.terms.len() }
+
+    pub fn is_empty(&self) -> bool { self.terms.is_empty() }
+
+    /// The cosine similarity of two tables, from 0 for tables with no terms in common to 1 for tables whose counts
+    /// are in the same proportions. Empty tables are 0 from everything.
     pub fn compare(&self, other: &Self) -> f64 {
-        let num = self.mul(other);
-        let denom = self.abs() * other.abs();
+        let denom = self.norm * other.norm;
         if denom == 0.0 {
             0.0
         } else {
-            num / denom
+            self.mul(other) / denom
         }
     }
 
-    pub fn sum(&self) -> f64 {
-        let mut acc = 0.0;
-        for key in self.0.keys() {
-            acc += *self.0.get(key).unwrap() as f64;
-        }
-        acc
+    /// Compares this table to each of others, see compare. Cheaper than calling compare for each when there
+    /// are many others, since this table is only looked at once.
+    pub fn compare_all<'a, I: Iterator<Item=&'a FrequencyTable>>(&self, others: I) -> Vec<f64> {
+        let counts = self.terms.iter().map(|&(id, freq)| (id, freq as f64)).collect::<HashMap<u32, f64>>();
+        others.map(|other| {
+            let denom = self.norm * other.norm;
+            if denom == 0.0 { return 0.0 }
+            let mut dot = 0.0;
+            for &(id, freq) in other.terms.iter() {
+                if let Some(count) = counts.get(&id) {
+                    dot += count * freq as f64;
+                }
+            }
+            dot / denom
+        }).collect()
     }
 
-    pub fn abs(&self) -> f64 {
-        let mut acc = 0.0;
-        for key in self.0.keys() {
-            let freq = *self.0.get(key).unwrap();
-            acc += (freq * freq) as f64;
+    /// The sum of all of the counts.
+    pub fn sum(&self) -> f64 { self.sum }
+
+    /// The magnitude of the table as a vector.
+    pub fn abs(&self) -> f64 { self.norm }
+
+    /// Calls f with the counts in self and rhs of every term they have in common, in order of term id.
+    pub fn join<F: FnMut(u32, f32, f32)>(&self, rhs: &Self, mut f: F) {
+        let (mut i, mut j) = (0, 0);
+        while i < self.terms.len() && j < rhs.terms.len() {
+            let (a, b) = (self.terms[i], rhs.terms[j]);
+            if a.0 < b.0 {
+                i += 1;
+            } else if a.0 > b.0 {
+                j += 1;
+            } else {
+                f(a.0, a.1, b.1);
+                i += 1;
+                j += 1;
+            }
         }
-        acc.sqrt()
     }
 
+    /// The dot product of two tables.
     pub fn mul(&self, rhs: &Self) -> f64 {
         let mut acc = 0.0;
-        for key in self.0.keys() {
-            if rhs.0.contains_key(key) {
-                acc += (self.0.get(key).unwrap() * rhs.0.get(key).unwrap()) as f64;
-            }
-        }
+        self.join(rhs, |_, a, b| acc += a as f64 * b as f64);
         acc
     }
 }
+
+impl Serialize for FrequencyTable {
+    type DeserializeOutput = FrequencyTable;
+
+    /// Each entry is packed into a single u64, the term id in the high half and the bits of the count in the low half.
+    fn deserialize(from: &mut Read) -> Result<FrequencyTable, io::Error> {
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut v = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let packed;
+            check!(u64::deserialize(from), packed);
+            v.push(((packed >> 32) as u32, f32::from_bits(packed as u32)));
+        }
+        Ok(FrequencyTable::from_sorted(v))
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.terms.len().serialize(to));
+        for &(id, freq) in self.terms.iter() {
+            check!((((id as u64) << 32) | freq.to_bits() as u64).serialize(to));
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self) -> u64 {
+        8 + 8 * self.terms.len() as u64
+    }
+}
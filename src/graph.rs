@@ -1,31 +1,114 @@
 use random_access_file::Serialize;
 use cfile_rs::CFile;
 use std::hash::Hash;
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet, VecDeque };
 use std::sync::Arc;
-use std::ops::Deref;
-use std::{ u64, f64 };
+use std::{ u64, f64, usize };
 use priority_queue::{ PriorityQueue, GraphNode };
+use edge::Edge;
+use heuristic::Heuristic;
 use std::io;
 use std::io::{ Write, Read };
 use node::Node;
 use persistable_hash::PersistableHashMap;
-#[macro_use]
-use check;
 /// A graph represented by a hashmap of Nodes. The nodes reference each other.
 pub struct Graph {
     pub nodes: Vec<Node>,
-    marker: usize,
+    /// The edges leading into each node. The `to` of each of these is the node the edge comes from.
+    reverse: Vec<Vec<Edge>>,
+}
+
+/// The state of one search through a graph: how far each node is from where it began, the node before each on
+/// the way there and whether the search is done with each. Every query has its own, so queries only need &self
+/// and can run at the same time.
+pub struct Workspace {
+    pub dist: Vec<f64>,
+    pub prev: Vec<usize>,
+    pub settled: Vec<bool>
+}
+
+impl Workspace {
+    /// A search of a graph with n nodes beginning at from.
+    pub fn new(n: usize, from: usize) -> Workspace {
+        let mut dist = vec![f64::INFINITY; n];
+        dist[from] = 0.0;
+        Workspace { dist: dist, prev: vec![usize::MAX; n], settled: vec![false; n] }
+    }
+
+    /// The path from the beginning of the search to to and its length, if the search reached to.
+    pub fn path_to(&self, to: usize) -> Option<(Vec<usize>, f64)> {
+        if !self.dist[to].is_finite() { return None }
+        let mut path = vec![to];
+        let mut current = to;
+        while self.prev[current] != usize::MAX {
+            current = self.prev[current];
+            path.push(current);
+        }
+        path.reverse();
+        Some((path, self.dist[to]))
+    }
+}
+
+/// PageRank stops iterating after this many iterations even if it hasn't converged.
+const PAGERANK_MAX_ITERATIONS: usize = 200;
+
+/// HITS stops iterating after this many iterations even if it hasn't converged.
+const HITS_MAX_ITERATIONS: usize = 200;
+
+/// How many of the nodes linking to each seed a query-focused HITS adds to its subgraph. Popular pages are
+/// linked to from so many others that taking all of them would make the subgraph most of the graph.
+const HITS_MAX_INBOUND: usize = 50;
+
+/// Follows prev back from to until it reaches from, returning the nodes in between in order.
+fn unwind(prev: &[usize], from: usize, to: usize) -> Vec<usize> {
+    let mut path = vec![to];
+    let mut current = to;
+    while current != from {
+        current = prev[current];
+        path.push(current);
+    }
+    path.reverse();
+    path
 }
 
 /// Public methods for Graph
-impl<'a> Graph {
+impl Graph {
     pub fn new() -> Self {
-        Graph { nodes: vec![], marker: 0 }
+        Graph { nodes: vec![], reverse: vec![] }
     }
 
     pub fn count(&self) -> usize { self.nodes.len() }
 
+    /// The number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.nodes.iter().map(|x| x.edges.len()).sum()
+    }
+
+    /// A hash of every node's edges and their weights, in order of node id, to tell whether data computed on a
+    /// graph (e.g. landmark distances) still fits it. Rebuilding a graph numbers its nodes differently and picking
+    /// another metric changes its weights, both of which change the fingerprint.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, which unlike the standard library's hasher is guaranteed to hash the same way forever.
+        let mut hash = 0xcbf29ce484222325u64;
+        {
+            let mut add = |x: u64| for i in 0..8 {
+                hash = (hash ^ ((x >> (8 * i)) & 0xff)).wrapping_mul(0x100000001b3);
+            };
+            add(self.nodes.len() as u64);
+            for node in self.nodes.iter() {
+                add(node.edges.len() as u64);
+                for edge in node.edges.iter() {
+                    add(edge.to as u64);
+                    add(edge.weight.to_bits());
+                }
+            }
+        }
+        hash
+    }
+
+    /// The edges leading into node, each with `to` set to the node it comes from.
+    pub fn inbound(&self, node: usize) -> &[Edge] { &self.reverse[node] }
+
     /// Returns the weight to get from one edge to another, returns None if there is no connection
     pub fn weight(&self, from: usize, to: usize) -> Option<f64> {
         for edge in self.nodes[from].edges.iter() {
@@ -41,6 +124,7 @@ impl<'a> Graph {
         let n = self.nodes.len();
         let node = Node::new(n);
         self.nodes.push(node);
+        self.reverse.push(vec![]);
         n
     }
 
@@ -49,144 +133,858 @@ impl<'a> Graph {
     pub fn connect(&mut self, from: usize, to: usize, weight: f64) -> Option<()> {
         if self.nodes.len() > from && self.nodes.len() > to {
             self.nodes[from].connect_with(to, weight);
+            self.reverse[to].push(Edge::new(from, weight));
             Some(())
         } else {
             None
         }
     }
 
-    /// Creates a min spanning tree
-    pub fn min_spanning_tree(&mut self, from: usize) -> Option<ResultTree> {
-        if from >= self.nodes.len() { return None }
-
-        self.marker += 1;
-        self.nodes[from].marker = self.marker;
+    /// Finds a minimum spanning tree of the nodes connected to from with Prim's algorithm, treating every edge as
+    /// going both ways. Returns the edges of the tree as (parent, child, weight), or None if from isn't in the graph.
+    pub fn min_spanning_tree(&self, from: usize) -> Option<Vec<(usize, usize, f64)>> {
+        let n = self.nodes.len();
+        if from >= n { return None }
 
         let mut pq = PriorityQueue::new();
-        let mut dist = vec![f64::INFINITY; self.nodes.len()];
+        // The lightest edge from a node in the tree to each node that isn't yet, and the node it comes from.
+        let mut best = vec![f64::INFINITY; n];
+        let mut parent = vec![usize::MAX; n];
+        let mut in_tree = vec![false; n];
+        let mut tree = vec![];
+        best[from] = 0.0;
         pq.push(GraphNode::new(from, 0.0));
-        dist[from] = 0.0;
 
-        while !pq.empty() {
-            let top = pq.poll().unwrap();
-            let current_min = top.0;
-            self.nodes[current_min].marker = self.marker;
-            for i in 0..self.nodes[current_min].edges.len() {
-                let dest = self.nodes[current_min].edges[i].to;
-                let weight = self.nodes[current_min].edges[i].weight;
-                if dist[dest] > weight {
-                    self.nodes[dest].next = current_min;
-                    dist[dest] = weight;
-                    pq.push(GraphNode(dest, dist[dest]));
+        while let Some(GraphNode(current, weight)) = pq.poll() {
+            if in_tree[current] || weight > best[current] { continue }
+            in_tree[current] = true;
+            if current != from {
+                tree.push((parent[current], current, weight));
+            }
+            for edge in self.nodes[current].edges.iter().chain(self.reverse[current].iter()) {
+                if !in_tree[edge.to] && edge.weight < best[edge.to] {
+                    best[edge.to] = edge.weight;
+                    parent[edge.to] = current;
+                    pq.push(GraphNode(edge.to, edge.weight));
                 }
             }
         }
-        Some(ResultTree::new(&*self, from))
+        Some(tree)
+    }
+
+    /// Finds a minimum spanning forest (a minimum spanning tree of each connected piece of the graph) with Kruskal's
+    /// algorithm, treating every edge as going both ways. Returns the edges as (from, to, weight), with from and to
+    /// as they were in the graph.
+    pub fn min_spanning_forest(&self) -> Vec<(usize, usize, f64)> {
+        let mut edges = vec![];
+        for node in self.nodes.iter() {
+            for edge in node.edges.iter() {
+                edges.push((node.id, edge.to, edge.weight));
+            }
+        }
+        edges.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+
+        // Union-find over the pieces of the forest so far, with path halving.
+        let mut pieces = (0..self.nodes.len()).collect::<Vec<usize>>();
+        fn find(pieces: &mut [usize], mut x: usize) -> usize {
+            while pieces[x] != x {
+                pieces[x] = pieces[pieces[x]];
+                x = pieces[x];
+            }
+            x
+        }
+        let mut forest = vec![];
+        for (from, to, weight) in edges.into_iter() {
+            let (a, b) = (find(&mut pieces, from), find(&mut pieces, to));
+            if a != b {
+                pieces[a] = b;
+                forest.push((from, to, weight));
+            }
+        }
+        forest
+    }
+
+    /// Finds a minimum spanning arborescence rooted at root with the Chu-Liu/Edmonds algorithm: the lightest set of
+    /// edges that gives every node reachable from root exactly one way in from root, following edge directions.
+    /// Returns the edges as (parent, child, weight), or None if root isn't in the graph.
+    pub fn min_arborescence(&self, root: usize) -> Option<Vec<(usize, usize, f64)>> {
+        if root >= self.nodes.len() { return None }
+
+        // Only the nodes reachable from root can be in the arborescence; they're numbered in the order found.
+        let hops = self.hop_distances(root, false);
+        let reachable = (0..self.nodes.len()).filter(|&x| hops[x] != usize::MAX).collect::<Vec<usize>>();
+        let mut number = vec![usize::MAX; self.nodes.len()];
+        for (i, &node) in reachable.iter().enumerate() {
+            number[node] = i;
+        }
+        let mut original = vec![];
+        for &node in reachable.iter() {
+            for edge in self.nodes[node].edges.iter() {
+                if edge.to != node && edge.to != root {
+                    original.push((node, edge.to, edge.weight));
+                }
+            }
+        }
+
+        // Each level is a graph with the cycles of the one before contracted into single nodes. Edges are
+        // (from, to, weight, the index of the edge they came from in the level before).
+        let mut edges = original.iter().enumerate()
+            .map(|(i, &(from, to, weight))| (number[from], number[to], weight, i))
+            .collect::<Vec<(usize, usize, f64, usize)>>();
+        let mut n = reachable.len();
+        let mut root_number = number[root];
+        // For each contracted level: its edges, the lightest edge into each node, and its cycles.
+        let mut levels = vec![];
+        let chosen;
+        loop {
+            let mut lightest = vec![usize::MAX; n];
+            for (i, edge) in edges.iter().enumerate() {
+                if edge.0 != edge.1 && edge.1 != root_number && (lightest[edge.1] == usize::MAX || edge.2 < edges[lightest[edge.1]].2) {
+                    lightest[edge.1] = i;
+                }
+            }
+
+            // Follow the lightest edges backwards from each node to find the cycles they make.
+            let mut walked = vec![usize::MAX; n];
+            let mut cycle_of = vec![usize::MAX; n];
+            let mut cycles: Vec<Vec<usize>> = vec![];
+            for start in 0..n {
+                let mut node = start;
+                while node != root_number && walked[node] == usize::MAX {
+                    walked[node] = start;
+                    node = edges[lightest[node]].0;
+                }
+                if node != root_number && walked[node] == start && cycle_of[node] == usize::MAX {
+                    let mut cycle = vec![node];
+                    cycle_of[node] = cycles.len();
+                    let mut next = edges[lightest[node]].0;
+                    while next != node {
+                        cycle_of[next] = cycles.len();
+                        cycle.push(next);
+                        next = edges[lightest[next]].0;
+                    }
+                    cycles.push(cycle);
+                }
+            }
+            if cycles.is_empty() {
+                chosen = (0..n).filter(|&x| x != root_number).map(|x| lightest[x]).collect::<Vec<usize>>();
+                levels.push((edges, lightest, cycles));
+                break;
+            }
+
+            // Contract each cycle into one node. Entering a cycle at a node means giving up the cycle's edge into
+            // it, so edges into cycles are made lighter by the weight of that edge.
+            let mut contracted = vec![usize::MAX; n];
+            for (i, cycle) in cycles.iter().enumerate() {
+                for &node in cycle.iter() {
+                    contracted[node] = i;
+                }
+            }
+            let mut next_n = cycles.len();
+            for x in contracted.iter_mut() {
+                if *x == usize::MAX {
+                    *x = next_n;
+                    next_n += 1;
+                }
+            }
+            let mut next_edges = vec![];
+            for (i, edge) in edges.iter().enumerate() {
+                let (from, to) = (contracted[edge.0], contracted[edge.1]);
+                if from == to { continue }
+                let weight = if cycle_of[edge.1] != usize::MAX { edge.2 - edges[lightest[edge.1]].2 } else { edge.2 };
+                next_edges.push((from, to, weight, i));
+            }
+            root_number = contracted[root_number];
+            n = next_n;
+            levels.push((edges, lightest, cycles));
+            edges = next_edges;
+        }
+
+        // Expand the cycles again, level by level. A cycle keeps all of its edges but the one into the node the
+        // chosen edge enters it at.
+        let (mut upper, _, _) = levels.pop().unwrap();
+        let mut chosen = chosen;
+        while let Some((edges, lightest, cycles)) = levels.pop() {
+            let mut expanded = chosen.iter().map(|&i| upper[i].3).collect::<Vec<usize>>();
+            let mut entered = vec![false; lightest.len()];
+            for &i in expanded.iter() {
+                entered[edges[i].1] = true;
+            }
+            for cycle in cycles.iter() {
+                for &node in cycle.iter() {
+                    if !entered[node] {
+                        expanded.push(lightest[node]);
+                    }
+                }
+            }
+            chosen = expanded;
+            upper = edges;
+        }
+        Some(chosen.into_iter().map(|i| original[upper[i].3]).collect())
     }
 
     /// Creates a shortest path tree using Dijkstra's algorithm
-    pub fn shortest_path_tree(&mut self, from: usize) -> Option<ResultTree> {
+    pub fn shortest_path_tree(&self, from: usize) -> Option<ResultTree> {
         if from >= self.nodes.len() { return None }
+        let search = self.dijkstra(from, false, usize::MAX);
+        Some(ResultTree::new(from, search))
+    }
 
-        self.marker += 1;
-        self.nodes[from].marker = self.marker;
-
+    /// Dijkstra's algorithm from from, following edges backwards if backward is true, until it settles until
+    /// (usize::MAX to search the whole graph).
+    fn dijkstra(&self, from: usize, backward: bool, until: usize) -> Workspace {
+        let mut search = Workspace::new(self.nodes.len(), from);
         let mut pq = PriorityQueue::new();
-        let mut dist = vec![f64::INFINITY; self.nodes.len()];
         pq.push(GraphNode::new(from, 0.0));
-        dist[from] = 0.0;
+        while let Some(GraphNode(current, d)) = pq.poll() {
+            if search.settled[current] { continue }
+            search.settled[current] = true;
+            if current == until { break }
+            let edges = if backward { &self.reverse[current] } else { &self.nodes[current].edges };
+            for edge in edges.iter() {
+                if d + edge.weight < search.dist[edge.to] {
+                    search.dist[edge.to] = d + edge.weight;
+                    search.prev[edge.to] = current;
+                    pq.push(GraphNode(edge.to, search.dist[edge.to]));
+                }
+            }
+        }
+        search
+    }
 
-        while !pq.empty() {
-            let top = pq.poll().unwrap();
-            let current_min = top.0;
-            for i in 0..self.nodes[current_min].edges.len() {
-                let dest = self.nodes[current_min].edges[i].to;
-                let weight = self.nodes[current_min].edges[i].weight;
-                if dist[dest] > dist[current_min] + weight {
-                    self.nodes[dest].marker = self.marker;
-                    self.nodes[dest].next = current_min;
-                    dist[dest] = dist[current_min] + weight;
-                    pq.push(GraphNode(dest, dist[dest]));
+    /// The distance from from to every node with Dijkstra's algorithm, or to from from every node if backward
+    /// is true. Nodes that can't be reached are infinitely far.
+    pub fn distances(&self, from: usize, backward: bool) -> Vec<f64> {
+        if from >= self.nodes.len() { return vec![f64::INFINITY; self.nodes.len()] }
+        self.dijkstra(from, backward, usize::MAX).dist
+    }
+
+    /// Finds the shortest path between two nodes. Returns None if no such path exists.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<(Vec<usize>, f64)> {
+        self.bidirectional_shortest_path(from, to)
+    }
+
+    /// Finds the shortest path between two nodes with Dijkstra's algorithm run from both ends at once, forwards
+    /// from from and backwards from to, until the searches meet. Returns None if no such path exists.
+    pub fn bidirectional_shortest_path(&self, from: usize, to: usize) -> Option<(Vec<usize>, f64)> {
+        let n = self.nodes.len();
+        if from >= n || to >= n { return None }
+        if from == to { return Some((vec![from], 0.0)) }
+
+        // Index 0 is the forward search, 1 the backward one.
+        let mut searches = [Workspace::new(n, from), Workspace::new(n, to)];
+        let mut queues = [PriorityQueue::new(), PriorityQueue::new()];
+        queues[0].push(GraphNode::new(from, 0.0));
+        queues[1].push(GraphNode::new(to, 0.0));
+
+        let mut best = f64::INFINITY;
+        let mut meeting = usize::MAX;
+        loop {
+            let tops = [queues[0].peek().map_or(f64::INFINITY, |x| x.1), queues[1].peek().map_or(f64::INFINITY, |x| x.1)];
+            // No path through a node that hasn't been settled by either search can be shorter than best.
+            if tops[0] + tops[1] >= best { break }
+            let side = if tops[0] <= tops[1] { 0 } else { 1 };
+            let GraphNode(current, d) = queues[side].poll().unwrap();
+            if searches[side].settled[current] || d > searches[side].dist[current] { continue }
+            searches[side].settled[current] = true;
+
+            let edges = if side == 0 { &self.nodes[current].edges } else { &self.reverse[current] };
+            for edge in edges.iter() {
+                let next = d + edge.weight;
+                if next < searches[side].dist[edge.to] {
+                    searches[side].dist[edge.to] = next;
+                    searches[side].prev[edge.to] = current;
+                    queues[side].push(GraphNode(edge.to, next));
+                }
+                let through = next + searches[1 - side].dist[edge.to];
+                if through < best {
+                    best = through;
+                    meeting = edge.to;
                 }
             }
         }
-        Some(ResultTree::new(&*self, from))
+        if meeting == usize::MAX { return None }
+
+        let mut path = unwind(&searches[0].prev, from, meeting);
+        let mut current = meeting;
+        while current != to {
+            current = searches[1].prev[current];
+            path.push(current);
+        }
+        Some((path, best))
     }
 
-    /// Finds the shortest path between two nodes using Prim's algorithm. Returns None if no such path exists.
-    pub fn shortest_path(&mut self, from: usize, to: usize) -> Option<(Vec<usize>, f64)> {
-        if from >= self.nodes.len() { return None }
+    /// The number of edges (hops) on the shortest path from from to every node, or to from from every node if
+    /// backward is true, ignoring weights. Nodes that can't be reached are usize::MAX hops away.
+    pub fn hop_distances(&self, from: usize, backward: bool) -> Vec<usize> {
+        let mut hops = vec![usize::MAX; self.nodes.len()];
+        if from >= self.nodes.len() { return hops }
+        let mut queue = VecDeque::new();
+        hops[from] = 0;
+        queue.push_back(from);
+        while let Some(current) = queue.pop_front() {
+            let edges = if backward { &self.reverse[current] } else { &self.nodes[current].edges };
+            for edge in edges.iter() {
+                if hops[edge.to] == usize::MAX {
+                    hops[edge.to] = hops[current] + 1;
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        hops
+    }
 
-        self.marker += 1;
-        self.nodes[from].marker = self.marker;
+    /// Which nodes can be reached from any of from.
+    pub fn reachable(&self, from: &[usize]) -> Vec<bool> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut queue = from.iter().cloned().filter(|&x| x < self.nodes.len()).collect::<VecDeque<usize>>();
+        for &x in queue.iter() { seen[x] = true; }
+        while let Some(current) = queue.pop_front() {
+            for edge in self.nodes[current].edges.iter() {
+                if !seen[edge.to] {
+                    seen[edge.to] = true;
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        seen
+    }
 
+    /// Finds a path with the fewest edges (hops) between two nodes with a breadth first search, ignoring weights.
+    pub fn hop_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let n = self.nodes.len();
+        if from >= n || to >= n { return None }
+        let mut prev = vec![usize::MAX; n];
+        let mut seen = vec![false; n];
+        let mut queue = VecDeque::new();
+        seen[from] = true;
+        queue.push_back(from);
+        while let Some(current) = queue.pop_front() {
+            if current == to { return Some(unwind(&prev, from, to)) }
+            for edge in self.nodes[current].edges.iter() {
+                if !seen[edge.to] {
+                    seen[edge.to] = true;
+                    prev[edge.to] = current;
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds a path with the fewest edges (hops) between two nodes with breadth first searches from both ends,
+    /// always growing the one with the smaller frontier by a whole layer, until they meet.
+    pub fn bidirectional_hop_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let n = self.nodes.len();
+        if from >= n || to >= n { return None }
+        if from == to { return Some(vec![from]) }
+
+        // Index 0 is the forward search, 1 the backward one.
+        let mut hops = [vec![usize::MAX; n], vec![usize::MAX; n]];
+        let mut prev = [vec![usize::MAX; n], vec![usize::MAX; n]];
+        let mut frontiers = [vec![from], vec![to]];
+        hops[0][from] = 0;
+        hops[1][to] = 0;
+        while !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+            let side = if frontiers[0].len() <= frontiers[1].len() { 0 } else { 1 };
+            let mut next = vec![];
+            // The whole layer is expanded before stopping, since a later node in it may meet the other search
+            // closer to its start.
+            let mut best = usize::MAX;
+            let mut meeting = usize::MAX;
+            for &current in frontiers[side].iter() {
+                let edges = if side == 0 { &self.nodes[current].edges } else { &self.reverse[current] };
+                for edge in edges.iter() {
+                    if hops[side][edge.to] == usize::MAX {
+                        hops[side][edge.to] = hops[side][current] + 1;
+                        prev[side][edge.to] = current;
+                        next.push(edge.to);
+                    }
+                    if hops[1 - side][edge.to] != usize::MAX && hops[side][edge.to] + hops[1 - side][edge.to] < best {
+                        best = hops[side][edge.to] + hops[1 - side][edge.to];
+                        meeting = edge.to;
+                    }
+                }
+            }
+            if meeting != usize::MAX {
+                let mut path = unwind(&prev[0], from, meeting);
+                let mut current = meeting;
+                while current != to {
+                    current = prev[1][current];
+                    path.push(current);
+                }
+                return Some(path);
+            }
+            frontiers[side] = next;
+        }
+        None
+    }
+
+    /// The nodes each node on a path with the fewest hops from from to to can go to next, i.e. the edges on any
+    /// such path, along with the number of hops. None if to can't be reached.
+    fn shortest_hop_edges(&self, from: usize, to: usize) -> Option<(usize, Vec<Vec<usize>>)> {
+        let n = self.nodes.len();
+        if from >= n || to >= n { return None }
+        let forward = self.hop_distances(from, false);
+        if forward[to] == usize::MAX { return None }
+        let backward = self.hop_distances(to, true);
+        let hops = forward[to];
+        let mut next = vec![vec![]; n];
+        for current in 0..n {
+            if forward[current] == usize::MAX || backward[current] == usize::MAX { continue }
+            for edge in self.nodes[current].edges.iter() {
+                if backward[edge.to] != usize::MAX && forward[current] + 1 + backward[edge.to] == hops {
+                    next[current].push(edge.to);
+                }
+            }
+            // Parallel edges would count the same path twice.
+            next[current].sort();
+            next[current].dedup();
+        }
+        Some((hops, next))
+    }
+
+    /// Finds every path with the fewest hops between two nodes, up to limit of them since there can be
+    /// exponentially many.
+    pub fn all_hop_paths(&self, from: usize, to: usize, limit: usize) -> Vec<Vec<usize>> {
+        let next = match self.shortest_hop_edges(from, to) {
+            Some((_, next)) => next,
+            None => return vec![]
+        };
+        let mut paths = vec![];
+        // Depth first, with the index of the next edge to try out of each node on the stack.
+        let mut stack = vec![(from, 0)];
+        while paths.len() < limit {
+            let (current, i) = match stack.last_mut() {
+                Some(top) => {
+                    top.1 += 1;
+                    (top.0, top.1 - 1)
+                },
+                None => break
+            };
+            if current == to {
+                paths.push(stack.iter().map(|x| x.0).collect());
+                stack.pop();
+            } else if i < next[current].len() {
+                stack.push((next[current][i], 0));
+            } else {
+                stack.pop();
+            }
+        }
+        paths
+    }
+
+    /// The number of hops on the shortest paths between two nodes and how many such paths there are (which
+    /// saturates at u64::MAX). None if to can't be reached.
+    pub fn count_hop_paths(&self, from: usize, to: usize) -> Option<(usize, u64)> {
+        let (hops, next) = match self.shortest_hop_edges(from, to) {
+            Some(x) => x,
+            None => return None
+        };
+        // Every edge in next goes one hop further from from, so counting in breadth first order sees all of the
+        // ways into a node before leaving it.
+        let mut counts = vec![0u64; self.nodes.len()];
+        counts[from] = 1;
+        let mut layer = vec![from];
+        let mut seen = vec![false; self.nodes.len()];
+        while !layer.is_empty() {
+            let mut following = vec![];
+            for &current in layer.iter() {
+                for &node in next[current].iter() {
+                    counts[node] = counts[node].saturating_add(counts[current]);
+                    if !seen[node] {
+                        seen[node] = true;
+                        following.push(node);
+                    }
+                }
+            }
+            layer = following;
+        }
+        Some((hops, counts[to]))
+    }
+
+    /// The weight of the lightest edge from one node to another, if they are connected.
+    fn lightest(&self, from: usize, to: usize) -> Option<f64> {
+        self.nodes[from].edges.iter().filter(|x| x.to == to).map(|x| x.weight).fold(None, |acc, x| match acc {
+            Some(y) if y <= x => Some(y),
+            _ => Some(x)
+        })
+    }
+
+    /// Dijkstra's algorithm from from to to, without going through banned nodes or using banned edges.
+    fn shortest_path_avoiding(&self, from: usize, to: usize, banned_nodes: &[bool], banned_edges: &HashSet<(usize, usize)>) -> Option<(Vec<usize>, f64)> {
+        let mut search = Workspace::new(self.nodes.len(), from);
         let mut pq = PriorityQueue::new();
-        let mut dist = vec![f64::INFINITY; self.nodes.len()];
         pq.push(GraphNode::new(from, 0.0));
-        dist[from] = 0.0;
+        while let Some(GraphNode(current, d)) = pq.poll() {
+            if search.settled[current] { continue }
+            if current == to { return search.path_to(to) }
+            search.settled[current] = true;
+            for edge in self.nodes[current].edges.iter() {
+                if banned_nodes[edge.to] || banned_edges.contains(&(current, edge.to)) { continue }
+                if d + edge.weight < search.dist[edge.to] {
+                    search.dist[edge.to] = d + edge.weight;
+                    search.prev[edge.to] = current;
+                    pq.push(GraphNode(edge.to, search.dist[edge.to]));
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds up to k of the shortest loopless paths between two nodes with Yen's algorithm, shortest first.
+    /// Each path after the first is the shortest one that leaves an earlier path somewhere (its spur node) and
+    /// doesn't go back through any of the nodes before that.
+    pub fn k_shortest_paths(&self, from: usize, to: usize, k: usize) -> Vec<(Vec<usize>, f64)> {
+        let mut found = match self.shortest_path(from, to) {
+            Some(x) if k > 0 => vec![x],
+            _ => return vec![]
+        };
+        let mut candidates: Vec<(Vec<usize>, f64)> = vec![];
+        let mut banned_nodes = vec![false; self.nodes.len()];
+        while found.len() < k {
+            let last = found[found.len() - 1].0.clone();
+            let mut root_weight = 0.0;
+            for i in 0..last.len() - 1 {
+                let root = &last[..i + 1];
+                // Edges that would lead back onto a path already found with the same root.
+                let mut banned_edges = HashSet::new();
+                for &(ref path, _) in found.iter() {
+                    if path.len() > i + 1 && &path[..i + 1] == root {
+                        banned_edges.insert((path[i], path[i + 1]));
+                    }
+                }
+                for &node in root[..i].iter() {
+                    banned_nodes[node] = true;
+                }
+                if let Some((spur, weight)) = self.shortest_path_avoiding(last[i], to, &banned_nodes, &banned_edges) {
+                    let mut path = root[..i].to_vec();
+                    path.extend(spur);
+                    if !candidates.iter().chain(found.iter()).any(|x| x.0 == path) {
+                        candidates.push((path, root_weight + weight));
+                    }
+                }
+                for &node in root[..i].iter() {
+                    banned_nodes[node] = false;
+                }
+                root_weight += self.lightest(last[i], last[i + 1]).unwrap_or(0.0);
+            }
+            if candidates.is_empty() { break }
+            let best = (0..candidates.len()).min_by(|&a, &b| {
+                candidates[a].1.partial_cmp(&candidates[b].1).unwrap().then_with(|| candidates[a].0.cmp(&candidates[b].0))
+            }).unwrap();
+            found.push(candidates.swap_remove(best));
+        }
+        found
+    }
+
+    /// Finds the shortest path between two nodes with A*, which settles nodes in order of their distance from
+    /// from plus heuristic's estimate of their distance to to. Returns None if no such path exists.
+    pub fn astar(&self, from: usize, to: usize, heuristic: &Heuristic) -> Option<(Vec<usize>, f64)> {
+        let n = self.nodes.len();
+        if from >= n || to >= n { return None }
+
+        let mut search = Workspace::new(n, from);
+        let mut pq = PriorityQueue::new();
+        pq.push(GraphNode::new(from, heuristic.estimate(from, to)));
+
+        while let Some(GraphNode(current, _)) = pq.poll() {
+            if search.settled[current] { continue }
+            if current == to { return search.path_to(to) }
+            search.settled[current] = true;
+            for edge in self.nodes[current].edges.iter() {
+                let next = search.dist[current] + edge.weight;
+                if next < search.dist[edge.to] {
+                    search.dist[edge.to] = next;
+                    search.prev[edge.to] = current;
+                    pq.push(GraphNode(edge.to, next + heuristic.estimate(edge.to, to)));
+                }
+            }
+        }
+        None
+    }
+
+    /// PageRank: how likely a surfer who follows a random link off each page, or with probability 1 - damping
+    /// (and always on pages without links) jumps to a random page, is to be on each page. Iterates until the
+    /// scores change by less than tolerance in total. The scores sum to 1.
+    pub fn pagerank(&self, damping: f64, tolerance: f64) -> Vec<f64> {
+        self.personalized_pagerank(&[], damping, tolerance)
+    }
+
+    /// PageRank where the surfer only ever jumps to one of seeds, which ranks nodes by how related they are to
+    /// the seeds. With no seeds this is plain PageRank.
+    pub fn personalized_pagerank(&self, seeds: &[usize], damping: f64, tolerance: f64) -> Vec<f64> {
+        let n = self.nodes.len();
+        let mut seeds = seeds.iter().cloned().filter(|&x| x < n).collect::<Vec<usize>>();
+        seeds.sort();
+        seeds.dedup();
+        let jumps = if seeds.is_empty() {
+            vec![1.0 / n as f64; n]
+        } else {
+            let mut jumps = vec![0.0; n];
+            for &seed in seeds.iter() {
+                jumps[seed] = 1.0 / seeds.len() as f64;
+            }
+            jumps
+        };
+
+        let mut rank = jumps.clone();
+        for _ in 0..PAGERANK_MAX_ITERATIONS {
+            let mut next = vec![0.0; n];
+            // The chance of being on a page without links, which is spread over the pages jumped to.
+            let mut dangling = 0.0;
+            for (i, node) in self.nodes.iter().enumerate() {
+                if node.edges.is_empty() {
+                    dangling += rank[i];
+                    continue;
+                }
+                let share = damping * rank[i] / node.edges.len() as f64;
+                for edge in node.edges.iter() {
+                    next[edge.to] += share;
+                }
+            }
+            let jump = 1.0 - damping + damping * dangling;
+            let mut change = 0.0;
+            for i in 0..n {
+                next[i] += jump * jumps[i];
+                change += (next[i] - rank[i]).abs();
+            }
+            rank = next;
+            if change < tolerance { break }
+        }
+        rank
+    }
+
+    /// HITS: the (hub, authority) score of every node. A good authority is linked to by good hubs and a good
+    /// hub links to good authorities, so lists of links score as hubs and the pages they list as authorities.
+    /// Iterates until the scores change by less than tolerance in total. Each set of scores sums to 1.
+    pub fn hits(&self, tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+        self.hits_within(&vec![true; self.nodes.len()], tolerance)
+    }
+
+    /// HITS on the neighbourhood of seeds: the seeds, the nodes they link to, and up to HITS_MAX_INBOUND of the
+    /// nodes linking to each of them. Nodes outside of it score 0. With no seeds every score is 0.
+    pub fn focused_hits(&self, seeds: &[usize], tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+        let mut within = vec![false; self.nodes.len()];
+        for &seed in seeds.iter().filter(|&&x| x < self.nodes.len()) {
+            within[seed] = true;
+            for edge in self.nodes[seed].edges.iter() {
+                within[edge.to] = true;
+            }
+            for edge in self.reverse[seed].iter().take(HITS_MAX_INBOUND) {
+                within[edge.to] = true;
+            }
+        }
+        self.hits_within(&within, tolerance)
+    }
+
+    /// Finds the strongly connected components (the largest groups of nodes that can each reach every other)
+    /// with Tarjan's algorithm. No edge leads from a component to one before it, i.e. the components are in
+    /// reverse topological order.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let n = self.nodes.len();
+        // The order nodes were found in, and the earliest found node still on the stack each can reach.
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut next = 0;
+        for root in 0..n {
+            if index[root] != usize::MAX { continue }
+            // Each node being searched and the index of the next of its edges to follow, in place of recursion
+            // which would overflow the stack on long paths.
+            let mut calls = vec![(root, 0)];
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            while let Some(&(current, i)) = calls.last() {
+                if i < self.nodes[current].edges.len() {
+                    calls.last_mut().unwrap().1 += 1;
+                    let to = self.nodes[current].edges[i].to;
+                    if index[to] == usize::MAX {
+                        index[to] = next;
+                        low[to] = next;
+                        next += 1;
+                        stack.push(to);
+                        on_stack[to] = true;
+                        calls.push((to, 0));
+                    } else if on_stack[to] && index[to] < low[current] {
+                        low[current] = index[to];
+                    }
+                    continue;
+                }
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    if low[current] < low[parent] { low[parent] = low[current]; }
+                }
+                if low[current] == index[current] {
+                    let mut component = vec![];
+                    loop {
+                        let x = stack.pop().unwrap();
+                        on_stack[x] = false;
+                        component.push(x);
+                        if x == current { break }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Finds the weakly connected components, the pieces the graph falls into if edges are treated as going both
+    /// ways.
+    pub fn weakly_connected_components(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut components = vec![];
+        for root in 0..self.nodes.len() {
+            if seen[root] { continue }
+            seen[root] = true;
+            let mut component = vec![root];
+            let mut i = 0;
+            while i < component.len() {
+                let current = component[i];
+                for edge in self.nodes[current].edges.iter().chain(self.reverse[current].iter()) {
+                    if !seen[edge.to] {
+                        seen[edge.to] = true;
+                        component.push(edge.to);
+                    }
+                }
+                i += 1;
+            }
+            components.push(component);
+        }
+        components
+    }
 
-        while !pq.empty() {
-            let top = pq.poll().unwrap();
-            let current_min = top.0;
-            for i in 0..self.nodes[current_min].edges.len() {
-                let dest = self.nodes[current_min].edges[i].to;
-                let weight = self.nodes[current_min].edges[i].weight;
-                if dist[dest] > dist[current_min] + weight {
-                    self.nodes[dest].marker = self.marker;
-                    self.nodes[dest].next = current_min;
-                    dist[dest] = dist[current_min] + weight;
-                    if dest == to { break; }
-                    pq.push(GraphNode(dest, dist[dest]));
+    /// Contracts each strongly connected component to a single node, see Condensation.
+    pub fn condensation(&self) -> Condensation {
+        let mut components = self.strongly_connected_components();
+        components.reverse();
+        let mut component = vec![0; self.nodes.len()];
+        for (i, members) in components.iter().enumerate() {
+            for &x in members.iter() { component[x] = i; }
+        }
+        let mut edges = vec![vec![]; components.len()];
+        for (i, members) in components.iter().enumerate() {
+            for &x in members.iter() {
+                for edge in self.nodes[x].edges.iter() {
+                    if component[edge.to] != i { edges[i].push(component[edge.to]); }
                 }
             }
+            edges[i].sort();
+            edges[i].dedup();
+        }
+        Condensation { components: components, component: component, edges: edges }
+    }
+
+    /// HITS on the subgraph of the nodes with within set.
+    fn hits_within(&self, within: &[bool], tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+        let n = self.nodes.len();
+        let count = within.iter().filter(|&&x| x).count();
+        let mut hubs = vec![0.0; n];
+        let mut authorities = vec![0.0; n];
+        if count == 0 { return (hubs, authorities) }
+        for i in (0..n).filter(|&x| within[x]) {
+            hubs[i] = 1.0 / count as f64;
         }
 
-        let mst = ResultTree::new(&*self, from);
-        mst.path_to(to)
+        // Scales scores to sum to 1, leaving them all 0 if they already are.
+        fn normalize(scores: &mut [f64]) {
+            let sum: f64 = scores.iter().sum();
+            if sum > 0.0 {
+                for x in scores.iter_mut() { *x /= sum; }
+            }
+        }
+        for _ in 0..HITS_MAX_ITERATIONS {
+            let mut next_authorities = vec![0.0; n];
+            for i in (0..n).filter(|&x| within[x]) {
+                next_authorities[i] = self.reverse[i].iter().filter(|x| within[x.to]).map(|x| hubs[x.to]).sum();
+            }
+            normalize(&mut next_authorities);
+            let mut next_hubs = vec![0.0; n];
+            for i in (0..n).filter(|&x| within[x]) {
+                next_hubs[i] = self.nodes[i].edges.iter().filter(|x| within[x.to]).map(|x| next_authorities[x.to]).sum();
+            }
+            normalize(&mut next_hubs);
+            let change: f64 = (0..n).map(|i| (next_hubs[i] - hubs[i]).abs() + (next_authorities[i] - authorities[i]).abs()).sum();
+            hubs = next_hubs;
+            authorities = next_authorities;
+            if change < tolerance { break }
+        }
+        (hubs, authorities)
     }
 }
 
-/// A structure that contains a min spainning tree, or a shortest path tree.
+/// A graph with each strongly connected component contracted to a single node, which leaves a directed acyclic
+/// graph. Components are in topological order: every edge leads from a component to a later one.
+pub struct Condensation {
+    /// The nodes in each component.
+    pub components: Vec<Vec<usize>>,
+    /// The component each node is in.
+    pub component: Vec<usize>,
+    /// The components each component has an edge to, without repeats.
+    pub edges: Vec<Vec<usize>>
+}
+
+impl Condensation {
+    pub fn len(&self) -> usize { self.components.len() }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.iter().map(|x| x.len()).sum()
+    }
+
+    /// The components no edge leads into, e.g. pages nothing links to.
+    pub fn sources(&self) -> Vec<usize> {
+        let mut has_inbound = vec![false; self.components.len()];
+        for edges in self.edges.iter() {
+            for &to in edges.iter() { has_inbound[to] = true; }
+        }
+        (0..self.components.len()).filter(|&x| !has_inbound[x]).collect()
+    }
+
+    /// The components no edge leads out of, e.g. pages without links.
+    pub fn sinks(&self) -> Vec<usize> {
+        (0..self.components.len()).filter(|&x| self.edges[x].is_empty()).collect()
+    }
+}
+
+/// A structure that contains a shortest path tree.
 /// Allows retreival of paths from the given root to another node.
-pub struct ResultTree<'a> {
-    graph: &'a Graph,
-    root: usize
+pub struct ResultTree {
+    root: usize,
+    search: Workspace
 }
 
-impl<'a> ResultTree<'a> {
-    pub fn new(graph: &'a Graph, root: usize) -> ResultTree<'a> {
-        ResultTree { graph: graph, root: root }
+impl ResultTree {
+    pub fn new(root: usize, search: Workspace) -> ResultTree {
+        ResultTree { root: root, search: search }
     }
 
-    pub fn path_to(&self, to: usize) -> Option<(Vec<usize>, f64)> {
-        if self.graph.nodes[to].marker != self.graph.marker { return None; }
+    pub fn root(&self) -> usize { self.root }
 
-        let mut ret = vec![to];
-        let mut weight = 0.0;
-        let mut ind = 0;
-        let mut current = &self.graph.nodes[to];
+    pub fn path_to(&self, to: usize) -> Option<(Vec<usize>, f64)> {
+        if to >= self.search.dist.len() { return None }
+        self.search.path_to(to)
+    }
 
-        while current.id != self.root {
-            ret.push(current.next);
-            let t = self.graph.weight(ret[ind + 1], ret[ind]);
-            log!("Debug", "w = {:?}", t);
-            if t.is_some() { weight += t.unwrap() }
-            current = &self.graph.nodes[current.next as usize];
-            ind += 1;
-        }
-        ret.reverse();
-        Some((ret, weight))
+    /// The path to to along with the weight of each edge on it, which is paired with the node the edge leads to.
+    pub fn path_to_with_weights(&self, to: usize) -> Option<(Vec<(usize, f64)>, f64)> {
+        self.path_to(to).map(|(path, distance)| {
+            let dist = &self.search.dist;
+            let weighted = path.iter().enumerate()
+                .map(|(i, &x)| (x, if i == 0 { 0.0 } else { dist[x] - dist[path[i - 1]] }))
+                .collect();
+            (weighted, distance)
+        })
     }
 
     /// Checks if the tree spans :-)
     pub fn spans(&self) -> bool {
-        for i in 0..self.graph.nodes.len() {
-            match self.path_to(i) {
-                Some(_) => continue,
-                None => return false
-            }
-        }
-        return true;
+        self.search.dist.iter().all(|x| x.is_finite())
     }
 }
 
@@ -200,7 +998,7 @@ impl<'a> Serialize for NodeList<'a> {
         let len;
         check!(u64::deserialize(read), len);
         let mut r = vec![];
-        for i in 0..len {
+        for _ in 0..len {
             let edge;
             check!(Node::deserialize(read), edge);
             r.push(edge);
@@ -228,11 +1026,17 @@ impl Serialize for Graph {
     type DeserializeOutput = Graph;
 
     fn deserialize(read: &mut Read) -> Result<Self, io::Error> {
-        let nodes;
+        let nodes: Vec<Node>;
         check!(NodeList::deserialize(read), nodes);
+        let mut reverse = (0..nodes.len()).map(|_| vec![]).collect::<Vec<Vec<Edge>>>();
+        for node in nodes.iter() {
+            for edge in node.edges.iter() {
+                reverse[edge.to].push(Edge::new(node.id, edge.weight));
+            }
+        }
         Ok(Graph {
             nodes: nodes,
-            marker: 0
+            reverse: reverse
         })
     }
 
@@ -252,69 +1056,35 @@ impl Serialize for Graph {
 
 pub struct AssociatedResultTree<'a, T: 'a + Eq + Hash> {
     graph: &'a AssociatedGraph<T>,
-    root: usize
+    tree: ResultTree
 }
 
 impl<'a, T> AssociatedResultTree<'a, T> where T: Eq + Hash {
-    pub fn new(graph: &'a AssociatedGraph<T>, root: usize) -> AssociatedResultTree<'a, T> {
-        AssociatedResultTree { graph: graph, root: root }
+    pub fn new(graph: &'a AssociatedGraph<T>, tree: ResultTree) -> AssociatedResultTree<'a, T> {
+        AssociatedResultTree { graph: graph, tree: tree }
     }
 
     pub fn path_to(&self, to: &T) -> Option<Path<T>> {
-        if self.graph.graph.nodes[self.graph.items[to]].marker != self.graph.graph.marker { return None }
-
-        let mut ret = vec![self.graph.items[to]];
-        let mut weight = 0.0;
-        let mut current = &self.graph.graph.nodes[ret[0]];
-        let mut ind = 0;
-        while current.id != self.root {
-            ret.push(current.next);
-            if let Some(x) = self.graph.graph.weight(ret[ind], ret[ind + 1]) {
-                weight += x;
-            }
-            current = &self.graph.graph.nodes[current.next];
-            ind += 1;
-        }
-
-        ret.reverse();
-        Some(Path {
-            path: ret.into_iter().map(|x| self.graph.lookup[&x].clone()).collect::<Vec<Arc<T>>>(),
-            distance: weight,
-        })
+        let to = match self.graph.index(to) {
+            Some(x) => x,
+            None => return None
+        };
+        self.graph.to_path(self.tree.path_to(to))
     }
 
     pub fn path_to_with_weight(&self, to: &T) -> Option<WeightedPath<T>> {
-        if self.graph.graph.nodes[self.graph.items[to]].marker != self.graph.graph.marker { return None }
-
-        let mut ret = vec![(self.graph.items[to], 0.0)];
-        let mut weight = 0.0;
-        let mut current = &self.graph.graph.nodes[self.graph.items[to]];
-        let mut ind = 0;
-        while current.id != self.root {
-            let next = current.next;
-            if let Some(x) = self.graph.graph.weight(ret[ind].0, next) {
-                weight += x;
-                ret.push((current.next, x));
-            }
-            current = &self.graph.graph.nodes[current.next as usize];
-            ind += 1;
-        }
-
-        ret.reverse();
-        Some(WeightedPath {
-            path: ret.into_iter().map(|x| (self.graph.lookup[&x.0].clone(), x.1)).collect::<Vec<(Arc<T>, f64)>>(),
-            distance: weight,
+        let to = match self.graph.index(to) {
+            Some(x) => x,
+            None => return None
+        };
+        self.tree.path_to_with_weights(to).map(|(path, distance)| WeightedPath {
+            path: path.into_iter().map(|x| (self.graph.lookup[&x.0].clone(), x.1)).collect::<Vec<(Arc<T>, f64)>>(),
+            distance: distance,
         })
     }
 
     pub fn spans(&self) -> bool {
-        for i in 0..self.graph.graph.nodes.len() {
-            match self.path_to(&*self.graph.lookup[&i]) {
-                Some(_) => continue,
-                None => return false
-            }
-        }
-        return true;
+        self.tree.spans()
     }
 }
 
@@ -323,19 +1093,53 @@ impl<'a, T> AssociatedResultTree<'a, T> where T: Eq + Hash {
 pub struct AssociatedGraph<T> where T: Hash + Eq {
     graph: Graph,
     items: HashMap<Arc<T>, usize>,
-    lookup: HashMap<usize, Arc<T>>
+    lookup: HashMap<usize, Arc<T>>,
+    /// Other keys that refer to the same node as a key in items, e.g. redirects to a page.
+    aliases: HashMap<T, usize>,
+    /// The name of the similarity metric the edge weights came from, empty if unknown.
+    metric: String
 }
 
 impl<T> AssociatedGraph<T> where T: Hash + Eq {
 
     /// Creates a new AssociatedGraph
     pub fn new() -> Self {
-        AssociatedGraph { graph: Graph::new(), items: HashMap::new(), lookup: HashMap::new() }
+        AssociatedGraph { graph: Graph::new(), items: HashMap::new(), lookup: HashMap::new(), aliases: HashMap::new(), metric: String::new() }
+    }
+
+    pub fn metric(&self) -> &str { &self.metric }
+
+    pub fn set_metric(&mut self, metric: String) { self.metric = metric; }
+
+    /// Finds the index of the node for key, following aliases.
+    fn index(&self, key: &T) -> Option<usize> {
+        match self.items.get(key) {
+            Some(x) => Some(*x),
+            None => self.aliases.get(key).copied()
+        }
+    }
+
+    /// Makes alias refer to the same node as canonical. Returns None if canonical isn't in the graph,
+    /// or if alias is already a key of its own.
+    pub fn alias(&mut self, alias: T, canonical: &T) -> Option<()> {
+        if self.items.contains_key(&alias) { return None }
+        match self.index(canonical) {
+            Some(ind) => {
+                self.aliases.insert(alias, ind);
+                Some(())
+            },
+            None => None
+        }
+    }
+
+    /// Returns the key of the node key refers to, i.e. the canonical key if key is an alias.
+    pub fn resolve(&self, key: &T) -> Option<Arc<T>> {
+        self.index(key).map(|x| self.lookup[&x].clone())
     }
 
     /// Returns a vec containing all of the keys found in the graph.
     pub fn keys(&self) -> Vec<Arc<T>> {
-        self.items.keys().into_iter().map(|x| x.clone()).collect()
+        self.items.keys().cloned().collect()
     }
 
     pub fn len(&self) -> usize { self.items.len() }
@@ -357,52 +1161,63 @@ impl<T> AssociatedGraph<T> where T: Hash + Eq {
     /// Connects two elements in the graph.
     /// If either of the keys arent in the Hash it will return Err(())
     pub fn connect(&mut self, from: &T, to: &T, weight: f64) -> Option<()> {
-        if self.items.contains_key(from) && self.items.contains_key(to) {
-            let from_ind = self.items[from];
-            let to_ind = self.items[to];
-            self.graph.connect(from_ind, to_ind, weight).unwrap();
-            Some(())
-        } else {
-            None
+        match (self.index(from), self.index(to)) {
+            (Some(from_ind), Some(to_ind)) => {
+                self.graph.connect(from_ind, to_ind, weight).unwrap();
+                Some(())
+            },
+            _ => None
         }
     }
 
     pub fn get_id(&self, key: &T) -> Option<usize> {
-        if self.contains_key(key) {
-            Some(self.items[key])
-        } else {
-            None
-        }
+        self.index(key)
     }
 
     pub fn contains_key(&self, has: &T) -> bool {
-        self.items.contains_key(has)
+        self.index(has).is_some()
     }
 
     /// Creates a shortest path tree
-    pub fn shortest_path_tree(&mut self, from: &T) -> Option<AssociatedResultTree<T>> {
-        if !self.items.contains_key(from) { return None }
-        let _ = self.graph.shortest_path_tree(self.items[from]);
-        Some(AssociatedResultTree {
-            graph: self,
-            root: self.items[from]
-        })
+    pub fn shortest_path_tree(&self, from: &T) -> Option<AssociatedResultTree<'_, T>> {
+        match self.index(from) {
+            Some(from) => self.graph.shortest_path_tree(from).map(|x| AssociatedResultTree::new(self, x)),
+            None => None
+        }
     }
 
-    /// Creates a min spanning tree
-    pub fn min_spanning_tree(&mut self, from: &T) -> Option<AssociatedResultTree<T>> {
-        if !self.items.contains_key(from) { return None }
-        let _ = self.graph.min_spanning_tree(self.items[from]);
-        Some(AssociatedResultTree {
-            graph: self,
-            root: self.items[from]
-        })
+    /// Turns tree edges between node indices into a SpanningTree of keys.
+    fn to_tree(&self, edges: Vec<(usize, usize, f64)>) -> SpanningTree<T> {
+        SpanningTree {
+            weight: edges.iter().map(|x| x.2).sum(),
+            edges: edges.into_iter().map(|(from, to, weight)| (self.lookup[&from].clone(), self.lookup[&to].clone(), weight)).collect()
+        }
+    }
+
+    /// Creates a min spanning tree of the keys connected to from, ignoring the direction of edges.
+    pub fn min_spanning_tree(&self, from: &T) -> Option<SpanningTree<T>> {
+        match self.index(from) {
+            Some(from) => self.graph.min_spanning_tree(from).map(|x| self.to_tree(x)),
+            None => None
+        }
+    }
+
+    /// Creates a min spanning forest of the whole graph, ignoring the direction of edges.
+    pub fn min_spanning_forest(&self) -> SpanningTree<T> {
+        self.to_tree(self.graph.min_spanning_forest())
+    }
+
+    /// Creates a min spanning arborescence of the keys reachable from root, following the direction of edges.
+    pub fn min_arborescence(&self, root: &T) -> Option<SpanningTree<T>> {
+        match self.index(root) {
+            Some(root) => self.graph.min_arborescence(root).map(|x| self.to_tree(x)),
+            None => None
+        }
     }
 
     /// Returns an iterator of all of the connections a given node has.
     pub fn connections(&self, k: &T) -> Option<Path<T>> {
-        if self.items.contains_key(k) {
-            let index = self.items[k];
+        if let Some(index) = self.index(k) {
             let mut v = Vec::with_capacity(self.graph.nodes[index].edges.len());
             for edge in self.graph.nodes[index].edges.iter() {
                 v.push(self.lookup[&edge.to].clone());
@@ -416,18 +1231,136 @@ impl<T> AssociatedGraph<T> where T: Hash + Eq {
         }
     }
 
-    pub fn shortest_path(&mut self, from: &T, to: &T) -> Option<Path<T>> {
-        if !(self.items.contains_key(from) && self.items.contains_key(to)) { return None }
-        let path = self.graph.shortest_path(self.items[from], self.items[to]);
-        if path.is_none() { return None }
-        let (p, distance) = path.unwrap();
-        let v = p.into_iter().map(|x| self.lookup[&x].clone()).collect::<Vec<Arc<T>>>();
-        let path = Path {
-            path: v,
+    /// Turns a path of node indices into a path of keys.
+    fn to_path(&self, path: Option<(Vec<usize>, f64)>) -> Option<Path<T>> {
+        path.map(|(p, distance)| Path {
+            path: p.into_iter().map(|x| self.lookup[&x].clone()).collect::<Vec<Arc<T>>>(),
             distance: distance,
-        };
-        Some(path)
+        })
+    }
+
+    /// Finds the shortest path between two keys.
+    pub fn shortest_path(&self, from: &T, to: &T) -> Option<Path<T>> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.to_path(self.graph.shortest_path(from, to)),
+            _ => None
+        }
+    }
+
+    /// Finds the shortest path between two keys with A*, guided by heuristic.
+    pub fn astar(&self, from: &T, to: &T, heuristic: &Heuristic) -> Option<Path<T>> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.to_path(self.graph.astar(from, to, heuristic)),
+            _ => None
+        }
+    }
+
+    /// Finds up to k of the shortest loopless paths between two keys, shortest first.
+    pub fn k_shortest_paths(&self, from: &T, to: &T, k: usize) -> Vec<Path<T>> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.graph.k_shortest_paths(from, to, k).into_iter()
+                .filter_map(|x| self.to_path(Some(x)))
+                .collect(),
+            _ => vec![]
+        }
+    }
+
+    /// Finds a path with the fewest hops between two keys, ignoring weights. The path's weight is the number of hops.
+    pub fn hop_path(&self, from: &T, to: &T) -> Option<Path<T>> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.to_path(self.graph.bidirectional_hop_path(from, to).map(|x| {
+                let hops = x.len() - 1;
+                (x, hops as f64)
+            })),
+            _ => None
+        }
+    }
+
+    /// Finds up to limit of the paths with the fewest hops between two keys.
+    pub fn all_hop_paths(&self, from: &T, to: &T, limit: usize) -> Vec<Path<T>> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.graph.all_hop_paths(from, to, limit).into_iter()
+                .filter_map(|x| {
+                    let hops = x.len() - 1;
+                    self.to_path(Some((x, hops as f64)))
+                })
+                .collect(),
+            _ => vec![]
+        }
     }
+
+    /// The number of hops on the shortest paths between two keys, and how many of them there are.
+    pub fn count_hop_paths(&self, from: &T, to: &T) -> Option<(usize, u64)> {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.graph.count_hop_paths(from, to),
+            _ => None
+        }
+    }
+
+    /// PageRank of every node, indexed by get_id. See Graph::pagerank
+    pub fn pagerank(&self, damping: f64, tolerance: f64) -> Vec<f64> {
+        self.graph.pagerank(damping, tolerance)
+    }
+
+    /// PageRank personalized to seeds, indexed by get_id. Seeds that aren't in the graph are ignored.
+    /// See Graph::personalized_pagerank
+    pub fn personalized_pagerank(&self, seeds: &[T], damping: f64, tolerance: f64) -> Vec<f64> {
+        let seeds = seeds.iter().filter_map(|x| self.index(x)).collect::<Vec<usize>>();
+        self.graph.personalized_pagerank(&seeds, damping, tolerance)
+    }
+
+    /// The (hub, authority) score of every node, indexed by get_id. See Graph::hits
+    pub fn hits(&self, tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+        self.graph.hits(tolerance)
+    }
+
+    /// HITS on the neighbourhood of seeds, indexed by get_id. Seeds that aren't in the graph are ignored.
+    /// See Graph::focused_hits
+    pub fn focused_hits(&self, seeds: &[T], tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+        let seeds = seeds.iter().filter_map(|x| self.index(x)).collect::<Vec<usize>>();
+        self.graph.focused_hits(&seeds, tolerance)
+    }
+
+    fn to_keys(&self, nodes: &[usize]) -> Vec<Arc<T>> {
+        nodes.iter().map(|x| self.lookup[x].clone()).collect()
+    }
+
+    /// The strongly connected components, in reverse topological order. See Graph::strongly_connected_components
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Arc<T>>> {
+        self.graph.strongly_connected_components().iter().map(|x| self.to_keys(x)).collect()
+    }
+
+    /// See Graph::weakly_connected_components
+    pub fn weakly_connected_components(&self) -> Vec<Vec<Arc<T>>> {
+        self.graph.weakly_connected_components().iter().map(|x| self.to_keys(x)).collect()
+    }
+
+    /// The condensation of the graph, with nodes indexed by get_id. See Graph::condensation
+    pub fn condensation(&self) -> Condensation {
+        self.graph.condensation()
+    }
+
+    /// The keys that can't be reached from any of from. Keys in from that aren't in the graph are ignored.
+    pub fn unreachable(&self, from: &[T]) -> Vec<Arc<T>> {
+        let from = from.iter().filter_map(|x| self.index(x)).collect::<Vec<usize>>();
+        let reachable = self.graph.reachable(&from);
+        let unreachable = (0..reachable.len()).filter(|&x| !reachable[x]).collect::<Vec<usize>>();
+        self.to_keys(&unreachable)
+    }
+
+    /// The n keys with the highest scores (indexed by get_id), highest first, leaving out the keys in exclude.
+    pub fn top(&self, scores: &[f64], n: usize, exclude: &[T]) -> Vec<(Arc<T>, f64)> {
+        let exclude = exclude.iter().filter_map(|x| self.index(x)).collect::<HashSet<usize>>();
+        let mut ranked = (0..scores.len().min(self.graph.count()))
+            .filter(|x| !exclude.contains(x))
+            .collect::<Vec<usize>>();
+        ranked.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap().then_with(|| a.cmp(&b)));
+        ranked.truncate(n);
+        ranked.into_iter().map(|x| (self.lookup[&x].clone(), scores[x])).collect()
+    }
+
+    /// The underlying graph, with nodes indexed by get_id.
+    pub fn graph(&self) -> &Graph { &self.graph }
 }
 
 impl<T> Serialize for AssociatedGraph<T> where T: Serialize + Eq + Hash, T::DeserializeOutput: Eq + Hash + Serialize {
@@ -436,7 +1369,7 @@ impl<T> Serialize for AssociatedGraph<T> where T: Serialize + Eq + Hash, T::Dese
     fn deserialize(read: &mut Read) -> Result<Self::DeserializeOutput, io::Error> {
         let graph;
         check!(Graph::deserialize(read), graph);
-        let mut temp_map: HashMap<T::DeserializeOutput, usize>;
+        let temp_map: HashMap<T::DeserializeOutput, usize>;
         check!(PersistableHashMap::<T, usize>::deserialize(read), temp_map);
         let mut items = HashMap::new();
         let mut lookup = HashMap::new();
@@ -445,10 +1378,15 @@ impl<T> Serialize for AssociatedGraph<T> where T: Serialize + Eq + Hash, T::Dese
             items.insert(rc.clone(), v);
             lookup.insert(v, rc);
         }
+        // Graphs persisted before aliases (or the metric) existed simply end early.
+        let aliases = PersistableHashMap::<T, usize>::deserialize(read).unwrap_or_default();
+        let metric = String::deserialize(read).unwrap_or_default();
         Ok(AssociatedGraph {
             graph: graph,
             items: items,
-            lookup: lookup
+            lookup: lookup,
+            aliases: aliases,
+            metric: metric
         })
     }
 
@@ -459,6 +1397,8 @@ impl<T> Serialize for AssociatedGraph<T> where T: Serialize + Eq + Hash, T::Dese
             check!((**k).serialize(to));
             check!(v.serialize(to));
         }
+        check!(PersistableHashMap::new(&self.aliases).serialize(to));
+        check!(self.metric.serialize(to));
         Ok(())
     }
 
@@ -467,7 +1407,7 @@ impl<T> Serialize for AssociatedGraph<T> where T: Serialize + Eq + Hash, T::Dese
         for (k, v) in self.items.iter() {
             sum += k.serialized_len() + v.serialized_len();
         }
-        sum
+        sum + PersistableHashMap::new(&self.aliases).serialized_len() + self.metric.serialized_len()
     }
 }
 
@@ -489,6 +1429,23 @@ impl<T> AssociatedGraph<T> where T: Hash + Eq + Serialize, T::DeserializeOutput:
     }
 }
 
+/// The edges of a spanning tree (or forest, or arborescence) as (from, to, weight).
+pub struct SpanningTree<T> where T: Hash + Eq {
+    edges: Vec<(Arc<T>, Arc<T>, f64)>,
+    weight: f64,
+}
+
+impl<T> SpanningTree<T> where T: Hash + Eq {
+    /// The sum of the weights of the edges.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    pub fn edges(&self) -> &[(Arc<T>, Arc<T>, f64)] {
+        &self.edges
+    }
+}
+
 pub struct Path<T> where T: Hash + Eq {
     path: Vec<Arc<T>>,
     distance: f64,
@@ -498,6 +1455,11 @@ impl<T> Path<T> where T: Hash + Eq {
     pub fn weight(&self) -> f64 {
         self.distance
     }
+
+    /// The keys along the path, in order from start to end. (Iterating over a Path goes from end to start.)
+    pub fn nodes(&self) -> &[Arc<T>] {
+        &self.path
+    }
 }
 
 impl<T> IntoIterator for Path<T> where T: Hash + Eq {
@@ -524,12 +1486,8 @@ impl<T> Iterator for IntoIter<T> where T: Hash + Eq {
 
     fn next(&mut self) -> Option<Arc<T>> {
         let x = self.pq.path.pop();
-        if x.is_some() {
-            self.index += 1;
-            Some(x.unwrap())
-        } else {
-            None
-        }
+        if x.is_some() { self.index += 1; }
+        x
     }
 }
 
@@ -568,11 +1526,218 @@ impl<T> Iterator for WeightedIntoIter<T> where T: Hash + Eq {
 
     fn next(&mut self) -> Option<(Arc<T>, f64)> {
         let x = self.pq.path.pop();
-        if x.is_some() {
-            self.index += 1;
-            Some(x.unwrap())
-        } else {
-            None
+        if x.is_some() { self.index += 1; }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(n: usize, edges: &[(usize, usize, f64)]) -> Graph {
+        let mut graph = Graph::new();
+        for _ in 0..n { graph.add(); }
+        for &(from, to, weight) in edges.iter() {
+            graph.connect(from, to, weight).unwrap();
+        }
+        graph
+    }
+
+    fn total(edges: &[(usize, usize, f64)]) -> f64 {
+        edges.iter().map(|x| x.2).sum()
+    }
+
+    /// 0 - 2 - 1 - 3 - 4 is the lightest spanning tree (1 + 2 + 5 + 3), skipping 0 - 1, 2 - 3, 2 - 4 and 4 - 0.
+    fn weighted() -> Vec<(usize, usize, f64)> {
+        vec![(0, 1, 4.0), (0, 2, 1.0), (2, 1, 2.0), (1, 3, 5.0), (2, 3, 8.0), (3, 4, 3.0), (2, 4, 9.0), (4, 0, 7.0)]
+    }
+
+    #[test]
+    fn prim_and_kruskal_agree_on_a_known_graph() {
+        let graph = graph(5, &weighted());
+        for from in 0..5 {
+            let tree = graph.min_spanning_tree(from).unwrap();
+            assert_eq!(tree.len(), 4);
+            assert_eq!(total(&tree), 11.0);
+        }
+        let forest = graph.min_spanning_forest();
+        assert_eq!(forest.len(), 4);
+        assert_eq!(total(&forest), 11.0);
+        assert!(graph.min_spanning_tree(5).is_none());
+    }
+
+    #[test]
+    fn forest_spans_every_piece_of_a_disconnected_graph() {
+        let mut edges = weighted();
+        edges.extend_from_slice(&[(5, 6, 2.0), (6, 7, 1.0), (5, 7, 4.0)]);
+        let graph = graph(9, &edges);
+        let forest = graph.min_spanning_forest();
+        // Node 8 has no edges, so it's a tree of its own without any.
+        assert_eq!(forest.len(), 6);
+        assert_eq!(total(&forest), 14.0);
+        assert_eq!(total(&graph.min_spanning_tree(0).unwrap()), 11.0);
+        assert_eq!(total(&graph.min_spanning_tree(7).unwrap()), 3.0);
+        assert!(graph.min_spanning_tree(8).unwrap().is_empty());
+    }
+
+    #[test]
+    fn arborescence_contracts_and_expands_a_cycle() {
+        // The lightest edges into 1 and 2 are each other's, so they have to be contracted. Entering the cycle at 1
+        // costs 5 - 1 against 6 - 1 at 2, so 0 -> 1 is kept along with 1 -> 2, and 2 -> 1 is dropped.
+        let graph = graph(4, &[(0, 1, 5.0), (0, 2, 6.0), (1, 2, 1.0), (2, 1, 1.0), (2, 3, 2.0), (1, 3, 4.0)]);
+        let mut tree = graph.min_arborescence(0).unwrap();
+        tree.sort_by_key(|x| x.1);
+        assert_eq!(tree, vec![(0, 1, 5.0), (1, 2, 1.0), (2, 3, 2.0)]);
+        assert!(graph.min_arborescence(4).is_none());
+    }
+
+    #[test]
+    fn arborescence_leaves_out_unreachable_nodes() {
+        let graph = graph(4, &[(0, 1, 1.0), (1, 0, 1.0), (2, 3, 1.0)]);
+        assert_eq!(graph.min_arborescence(0).unwrap(), vec![(0, 1, 1.0)]);
+    }
+
+    /// The classic example for Yen's algorithm, with C, D, E, F, G and H numbered 0 to 5.
+    fn yen() -> Graph {
+        graph(6, &[(0, 1, 3.0), (0, 2, 2.0), (1, 3, 4.0), (2, 1, 1.0), (2, 3, 2.0), (2, 4, 3.0), (3, 4, 2.0),
+                   (3, 5, 1.0), (4, 5, 2.0)])
+    }
+
+    #[test]
+    fn k_shortest_paths_are_loopless_and_in_order() {
+        let graph = yen();
+        let paths = graph.k_shortest_paths(0, 5, 3);
+        assert_eq!(paths[0], (vec![0, 2, 3, 5], 5.0));
+        assert_eq!(paths[1], (vec![0, 2, 4, 5], 7.0));
+        assert_eq!(paths[2].1, 8.0);
+
+        // There are only 7 loopless paths from C to H.
+        let paths = graph.k_shortest_paths(0, 5, 10);
+        assert_eq!(paths.iter().map(|x| x.1).collect::<Vec<_>>(), vec![5.0, 7.0, 8.0, 8.0, 8.0, 11.0, 11.0]);
+        let mut distinct = paths.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 7);
+        for &(ref path, _) in paths.iter() {
+            let mut nodes = path.clone();
+            nodes.sort();
+            nodes.dedup();
+            assert_eq!(nodes.len(), path.len());
+        }
+        let mut ties = paths.iter().filter(|x| x.1 == 8.0).map(|x| x.0.clone()).collect::<Vec<_>>();
+        ties.sort();
+        assert_eq!(ties, vec![vec![0, 1, 3, 5], vec![0, 2, 1, 3, 5], vec![0, 2, 3, 4, 5]]);
+
+        assert!(graph.k_shortest_paths(5, 0, 3).is_empty());
+        assert!(graph.k_shortest_paths(0, 5, 0).is_empty());
+        assert_eq!(graph.k_shortest_paths(0, 0, 3), vec![(vec![0], 0.0)]);
+    }
+
+    #[test]
+    fn pagerank_of_known_graphs() {
+        let cycle = graph(3, &[(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0)]);
+        for x in cycle.pagerank(0.85, 1e-12) {
+            assert!((x - 1.0 / 3.0).abs() < 1e-9);
+        }
+
+        // 1 has no links, so its surfer always jumps: r0 = 0.15 / 2 + 0.85 * r1 / 2, and r0 + r1 = 1.
+        let ranks = graph(2, &[(0, 1, 1.0)]).pagerank(0.85, 1e-12);
+        assert!((ranks[0] - 0.5 / 1.425).abs() < 1e-9);
+        assert!((ranks[1] - (1.0 - 0.5 / 1.425)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn personalized_pagerank_stays_near_the_seeds() {
+        let graph = graph(6, &[(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0), (3, 4, 1.0), (4, 5, 1.0)]);
+        let ranks = graph.personalized_pagerank(&[0], 0.85, 1e-12);
+        assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(ranks[0] > ranks[1] && ranks[1] > ranks[2]);
+        assert_eq!(&ranks[3..], &[0.0, 0.0, 0.0]);
+        assert_eq!(graph.personalized_pagerank(&[], 0.85, 1e-12), graph.pagerank(0.85, 1e-12));
+    }
+
+    /// 0 links to 1, 2 and 3 and 4 links to 1, next to the chain 5 -> 6 -> 7.
+    fn hubs_and_authorities() -> Graph {
+        graph(8, &[(0, 1, 1.0), (0, 2, 1.0), (0, 3, 1.0), (4, 1, 1.0), (5, 6, 1.0), (6, 7, 1.0)])
+    }
+
+    #[test]
+    fn hits_of_a_known_graph() {
+        // The hub scores of 0 and 4 are the principal eigenvector of [[3, 1], [1, 1]], (1 + sqrt 2, 1), and each
+        // authority scores the sum of the hubs linking to it. The chain's eigenvalue is smaller, so it fades out.
+        let (hubs, authorities) = hubs_and_authorities().hits(1e-12);
+        let root2 = 2f64.sqrt();
+        assert!((hubs[0] - 1.0 / root2).abs() < 1e-9);
+        assert!((hubs[4] - (1.0 - 1.0 / root2)).abs() < 1e-9);
+        assert!((authorities[1] - (root2 - 1.0)).abs() < 1e-9);
+        assert!((authorities[2] - (1.0 - 1.0 / root2)).abs() < 1e-9);
+        assert!((authorities[3] - authorities[2]).abs() < 1e-12);
+        assert_eq!(hubs[1], 0.0);
+        assert_eq!(authorities[0], 0.0);
+        for i in 5..8 {
+            assert!(hubs[i] < 1e-6 && authorities[i] < 1e-6);
+        }
+    }
+
+    #[test]
+    fn focused_hits_only_scores_the_neighbourhood() {
+        let graph = hubs_and_authorities();
+        let (hubs, authorities) = graph.focused_hits(&[6], 1e-12);
+        assert!(hubs[..5].iter().chain(authorities[..5].iter()).all(|&x| x == 0.0));
+        assert!((hubs[5] - 0.5).abs() < 1e-9 && (hubs[6] - 0.5).abs() < 1e-9);
+        assert!((authorities[6] - 0.5).abs() < 1e-9 && (authorities[7] - 0.5).abs() < 1e-9);
+        let (hubs, authorities) = graph.focused_hits(&[], 1e-12);
+        assert!(hubs.iter().chain(authorities.iter()).all(|&x| x == 0.0));
+    }
+
+    /// The cycle 0 -> 1 -> 2 -> 0 leads to the cycle 3 <-> 4, which leads to 6. 5 is on its own.
+    fn components() -> Graph {
+        graph(7, &[(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0), (2, 3, 1.0), (3, 4, 1.0), (4, 3, 1.0), (4, 6, 1.0)])
+    }
+
+    fn sorted(mut components: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        for component in components.iter_mut() { component.sort(); }
+        components.sort();
+        components
+    }
+
+    #[test]
+    fn strongly_connected_components_in_reverse_topological_order() {
+        let graph = components();
+        let scc = graph.strongly_connected_components();
+        assert_eq!(sorted(scc.clone()), vec![vec![0, 1, 2], vec![3, 4], vec![5], vec![6]]);
+        let position = |node: usize| scc.iter().position(|x| x.contains(&node)).unwrap();
+        assert!(position(6) < position(3) && position(3) < position(0));
+
+        assert_eq!(sorted(graph.weakly_connected_components()), vec![vec![0, 1, 2, 3, 4, 6], vec![5]]);
+        assert_eq!(graph.reachable(&[3]), vec![false, false, false, true, true, false, true]);
+    }
+
+    #[test]
+    fn condensation_is_a_dag_in_topological_order() {
+        let condensation = components().condensation();
+        assert_eq!(condensation.len(), 4);
+        assert_eq!(condensation.edge_count(), 2);
+        let (a, b, c) = (condensation.component[0], condensation.component[3], condensation.component[6]);
+        assert!(a < b && b < c);
+        assert_eq!(condensation.component[1], a);
+        assert_eq!(condensation.edges[a], vec![b]);
+        assert_eq!(condensation.edges[b], vec![c]);
+        let lonely = condensation.component[5];
+        assert!(condensation.sources().contains(&a) && condensation.sources().contains(&lonely));
+        assert!(condensation.sinks().contains(&c) && condensation.sinks().contains(&lonely));
+    }
+
+    #[test]
+    fn strongly_connected_components_of_a_long_cycle() {
+        // Deep enough that a recursive search would overflow the stack.
+        let n = 200000;
+        let mut graph = Graph::new();
+        for _ in 0..n { graph.add(); }
+        for i in 0..n {
+            graph.connect(i, (i + 1) % n, 1.0).unwrap();
         }
+        assert_eq!(graph.strongly_connected_components().len(), 1);
     }
 }
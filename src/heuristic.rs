@@ -0,0 +1,13 @@
+/// A lower bound on the distance between two nodes, used to guide A*. An estimate must never be more than the
+/// real distance (i.e. be admissible) or A* may return paths that aren't the shortest, and should be consistent,
+/// i.e. estimate(a, to) <= weight(a, b) + estimate(b, to) for every edge a -> b.
+pub trait Heuristic {
+    fn estimate(&self, node: usize, to: usize) -> f64;
+}
+
+/// Estimates every distance as 0, which makes A* behave like Dijkstra's algorithm.
+pub struct Zero;
+
+impl Heuristic for Zero {
+    fn estimate(&self, _node: usize, _to: usize) -> f64 { 0.0 }
+}
@@ -0,0 +1,180 @@
+use cfile_rs::CFile;
+use graph::Graph;
+use heuristic::Heuristic;
+use random_access_file::Serialize;
+use std::f64;
+use std::io;
+use std::io::{ Read, Write };
+
+/// The ways landmarks can be picked.
+pub const SELECTIONS: [&'static str; 2] = ["farthest", "degree"];
+
+/// Precomputed distances to and from a few landmark nodes, for the ALT (A*, landmarks, triangle inequality)
+/// heuristic. For any landmark L, d(v, to) >= d(L, to) - d(L, v) and d(v, to) >= d(v, L) - d(to, L), so the
+/// largest of these over every landmark is an admissible (and consistent) estimate.
+pub struct Landmarks {
+    /// The landmark nodes.
+    nodes: Vec<usize>,
+    /// from[i][v] is the distance from landmark i to v.
+    from: Vec<Vec<f64>>,
+    /// to[i][v] is the distance from v to landmark i.
+    to: Vec<Vec<f64>>,
+    /// The number of nodes in, and the fingerprint of, the graph the distances were computed on.
+    graph_nodes: u64,
+    graph: u64
+}
+
+impl Landmarks {
+    /// Computes the distances to and from each of nodes.
+    pub fn new(graph: &Graph, nodes: Vec<usize>) -> Landmarks {
+        let from = nodes.iter().map(|&x| graph.distances(x, false)).collect();
+        let to = nodes.iter().map(|&x| graph.distances(x, true)).collect();
+        Landmarks {
+            nodes: nodes,
+            from: from,
+            to: to,
+            graph_nodes: graph.count() as u64,
+            graph: graph.fingerprint()
+        }
+    }
+
+    /// Picks up to k landmarks far apart from one another: the first is the node with the most edges and each
+    /// one after is the node furthest from the closest landmark picked so far. Far apart landmarks on the edges of
+    /// the graph give the tightest bounds.
+    pub fn farthest(graph: &Graph, k: usize) -> Landmarks {
+        let n = graph.count();
+        let mut nodes: Vec<usize> = vec![];
+        if n == 0 || k == 0 { return Landmarks::new(graph, nodes) }
+        let mut from = vec![];
+        let mut to = vec![];
+        // The distance from each node to the closest landmark, ignoring landmarks it can't be reached from.
+        let mut closest = vec![f64::INFINITY; n];
+        let mut next = (0..n).max_by_key(|&x| graph.nodes[x].edges.len() + graph.inbound(x).len()).unwrap();
+        loop {
+            let dist = graph.distances(next, false);
+            for (v, d) in dist.iter().enumerate() {
+                if *d < closest[v] { closest[v] = *d; }
+            }
+            nodes.push(next);
+            from.push(dist);
+            to.push(graph.distances(next, true));
+            if nodes.len() == k { break }
+            // Nodes no landmark reaches are in small pieces of the graph that paths rarely go through.
+            next = match (0..n).filter(|x| closest[*x].is_finite() && !nodes.contains(x))
+                .max_by(|a, b| closest[*a].partial_cmp(&closest[*b]).unwrap()) {
+                Some(x) => x,
+                None => break
+            };
+        }
+        Landmarks { nodes: nodes, from: from, to: to, graph_nodes: n as u64, graph: graph.fingerprint() }
+    }
+
+    /// Picks the k nodes with the most edges in or out.
+    pub fn by_degree(graph: &Graph, k: usize) -> Landmarks {
+        let mut nodes = (0..graph.count()).collect::<Vec<usize>>();
+        nodes.sort_by_key(|&x| ::std::usize::MAX - (graph.nodes[x].edges.len() + graph.inbound(x).len()));
+        nodes.truncate(k);
+        Landmarks::new(graph, nodes)
+    }
+
+    /// Picks k landmarks with selection, one of SELECTIONS.
+    pub fn select(graph: &Graph, k: usize, selection: &str) -> Option<Landmarks> {
+        match selection {
+            "farthest" => Some(Landmarks::farthest(graph, k)),
+            "degree" => Some(Landmarks::by_degree(graph, k)),
+            _ => None
+        }
+    }
+
+    pub fn len(&self) -> usize { self.nodes.len() }
+
+    /// Returns true if the landmarks were computed on graph, going by its fingerprint. Landmarks from another
+    /// graph, or the same one rebuilt, would give estimates that aren't admissible.
+    pub fn fits(&self, graph: &Graph) -> bool {
+        self.graph_nodes == graph.count() as u64 && self.graph == graph.fingerprint()
+    }
+
+    pub fn persist(&self, to: &str) -> Result<(), io::Error> {
+        let mut file;
+        check!(CFile::open(to, "w+"), file);
+        check!(self.serialize(&mut file));
+        check!(file.flush());
+        Ok(())
+    }
+
+    pub fn from_disk(from: &str) -> Result<Landmarks, io::Error> {
+        let mut file;
+        check!(CFile::open(from, "r+"), file);
+        Landmarks::deserialize(&mut file)
+    }
+}
+
+/// The lower bound on the distance from one node to another that one landmark gives, where a and b are distances
+/// through it. If b is infinite there is no bound, but if only a is the other node can't be reached at all.
+fn bound(a: f64, b: f64) -> f64 {
+    if b.is_infinite() { 0.0 } else { a - b }
+}
+
+impl Heuristic for Landmarks {
+    fn estimate(&self, node: usize, to: usize) -> f64 {
+        let mut best = 0.0;
+        for i in 0..self.nodes.len() {
+            let estimate = bound(self.from[i][to], self.from[i][node]).max(bound(self.to[i][node], self.to[i][to]));
+            if estimate > best { best = estimate; }
+        }
+        best
+    }
+}
+
+fn deserialize_distances(from: &mut Read, n: u64) -> Result<Vec<f64>, io::Error> {
+    let mut v = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+        let d;
+        check!(f64::deserialize(from), d);
+        v.push(d);
+    }
+    Ok(v)
+}
+
+impl Serialize for Landmarks {
+    type DeserializeOutput = Landmarks;
+
+    fn deserialize(from: &mut Read) -> Result<Landmarks, io::Error> {
+        let graph;
+        check!(u64::deserialize(from), graph);
+        let graph_nodes;
+        check!(u64::deserialize(from), graph_nodes);
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut landmarks = Landmarks { nodes: vec![], from: vec![], to: vec![], graph_nodes: graph_nodes, graph: graph };
+        for _ in 0..len {
+            let node;
+            check!(u64::deserialize(from), node);
+            let dist_from;
+            check!(deserialize_distances(from, graph_nodes), dist_from);
+            let dist_to;
+            check!(deserialize_distances(from, graph_nodes), dist_to);
+            landmarks.nodes.push(node as usize);
+            landmarks.from.push(dist_from);
+            landmarks.to.push(dist_to);
+        }
+        Ok(landmarks)
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.graph.serialize(to));
+        check!(self.graph_nodes.serialize(to));
+        check!(self.nodes.len().serialize(to));
+        for i in 0..self.nodes.len() {
+            check!(self.nodes[i].serialize(to));
+            for d in self.from[i].iter().chain(self.to[i].iter()) {
+                check!(d.serialize(to));
+            }
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self) -> u64 {
+        24 + self.nodes.len() as u64 * (8 + 16 * self.graph_nodes)
+    }
+}
@@ -0,0 +1,239 @@
+use random_access_file::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::io::{ Read, Write };
+use unicode_normalization::UnicodeNormalization;
+
+/// Namespaces whose pages aren't articles. Their talk namespaces ("User_talk:" etc.) are caught separately.
+const NON_ARTICLE_NAMESPACES: [&'static str; 18] = [
+    "media", "special", "talk", "user", "wikipedia", "wp", "project", "file", "image", "mediawiki",
+    "template", "help", "category", "portal", "draft", "timedtext", "module", "book"
+];
+
+/// The prefix all article urls share.
+pub const WIKI_PREFIX: &'static str = "/wiki/";
+
+/// Decodes %XX escapes. Returns None if the escapes don't decode to valid utf-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = &bytes[i + 1..i + 3];
+            let byte = ::std::str::from_utf8(hex).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = byte {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Percent encodes the parts of an article url that can't appear raw in a request path,
+/// e.g. "/wiki/Gödel's_theorem" becomes "/wiki/G%C3%B6del's_theorem"
+pub fn encode(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for byte in url.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' |
+            b'-' | b'_' | b'.' | b'~' | b'/' | b':' | b'(' | b')' | b',' | b'\'' | b'!' | b'*' | b';' | b'@' | b'$' | b'&' | b'=' | b'+' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    encoded
+}
+
+/// Returns true if title is in the article namespace, i.e. it isn't something like "File:Sun.png" or "User_talk:Foo"
+pub fn is_article_title(title: &str) -> bool {
+    match title.find(':') {
+        Some(ind) => {
+            let prefix = title[..ind].trim().replace(' ', "_").to_lowercase();
+            !(NON_ARTICLE_NAMESPACES.contains(&prefix.as_str()) || prefix.ends_with("_talk"))
+        },
+        None => true
+    }
+}
+
+/// Converts a decoded page title (e.g. "solar system") into its article url ("/wiki/Solar_system"): spaces become
+/// underscores, the text is NFC normalized and the first letter is capitalized, as MediaWiki does.
+pub fn title_to_url(title: &str) -> String {
+    let title = title.nfc().collect::<String>().replace(' ', "_");
+    let title = title.trim_matches('_');
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}{}", WIKI_PREFIX, first.to_uppercase().collect::<String>(), chars.as_str()),
+        None => WIKI_PREFIX.to_string()
+    }
+}
+
+/// Normalizes an href found in a page into the url of the article it links to, or None if it doesn't link to
+/// an article. Fragments and query strings are dropped, escapes are decoded, and the title is normalized as in
+/// title_to_url, so "/wiki/g%C3%B6del%27s_theorem#Proof" becomes "/wiki/Gödel's_theorem".
+pub fn normalize(href: &str) -> Option<String> {
+    if !href.starts_with(WIKI_PREFIX) { return None }
+    let mut title = &href[WIKI_PREFIX.len()..];
+    // Escaped # and ? are part of the title, so these have to be stripped before decoding.
+    if let Some(ind) = title.find('#') { title = &title[..ind]; }
+    if let Some(ind) = title.find('?') { title = &title[..ind]; }
+    let title = match percent_decode(title) {
+        Some(x) => x.replace('_', " "),
+        None => return None
+    };
+    let title = title.trim();
+    if title.is_empty() || !is_article_title(title) { return None }
+    Some(title_to_url(title))
+}
+
+/// Where on a page a link appears, from most to least prominent.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum LinkRegion {
+    /// Before the first section heading.
+    Lead,
+    Infobox,
+    Body,
+    /// Navigation boxes and sidebars, which are mostly shared with many other pages.
+    Navbox
+}
+
+impl LinkRegion {
+    fn from_u8(x: u8) -> Option<LinkRegion> {
+        match x {
+            0 => Some(LinkRegion::Lead),
+            1 => Some(LinkRegion::Infobox),
+            2 => Some(LinkRegion::Body),
+            3 => Some(LinkRegion::Navbox),
+            _ => None
+        }
+    }
+}
+
+/// A link from one page to another, along with how it appears on the page. A page has one Link per article it links to,
+/// however many times it links there.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Link {
+    /// The normalized url of the article linked to.
+    pub url: String,
+    /// The distinct anchor texts the link appears with, in the order they first appear.
+    pub anchors: Vec<String>,
+    /// The most prominent region the link appears in.
+    pub region: LinkRegion,
+    /// How many times the link appears on the page.
+    pub count: u64
+}
+
+impl Link {
+    /// A single occurrence of a link.
+    pub fn new(url: String, anchor: &str, region: LinkRegion) -> Link {
+        let anchor = anchor.split_whitespace().collect::<Vec<_>>().join(" ");
+        Link {
+            url: url,
+            anchors: if anchor.is_empty() { vec![] } else { vec![anchor] },
+            region: region,
+            count: 1
+        }
+    }
+
+    /// Folds other, another occurrence of the same link, into this one.
+    pub fn merge(&mut self, other: Link) {
+        for anchor in other.anchors.into_iter() {
+            if !self.anchors.contains(&anchor) {
+                self.anchors.push(anchor);
+            }
+        }
+        self.region = self.region.min(other.region);
+        self.count += other.count;
+    }
+}
+
+/// Merges repeated links to the same url, keeping the order in which urls first appear.
+pub fn dedup(occurrences: Vec<Link>) -> Vec<Link> {
+    let mut links: Vec<Link> = Vec::with_capacity(occurrences.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for link in occurrences.into_iter() {
+        if let Some(&ind) = index.get(&link.url) {
+            links[ind].merge(link);
+            continue;
+        }
+        index.insert(link.url.clone(), links.len());
+        links.push(link);
+    }
+    links
+}
+
+impl Serialize for Link {
+    type DeserializeOutput = Link;
+    fn deserialize(from: &mut Read) -> Result<Self, io::Error> {
+        let url;
+        check!(String::deserialize(from), url);
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut anchors = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let anchor;
+            check!(String::deserialize(from), anchor);
+            anchors.push(anchor);
+        }
+        let region;
+        check!(u8::deserialize(from), region);
+        let region = match LinkRegion::from_u8(region) {
+            Some(x) => x,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown link region {}", region)))
+        };
+        let count;
+        check!(u64::deserialize(from), count);
+        Ok(Link {
+            url: url,
+            anchors: anchors,
+            region: region,
+            count: count
+        })
+    }
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.url.serialize(to));
+        check!(self.anchors.len().serialize(to));
+        for anchor in self.anchors.iter() {
+            check!(anchor.serialize(to));
+        }
+        check!((self.region as u8).serialize(to));
+        self.count.serialize(to)
+    }
+    fn serialized_len(&self) -> u64 {
+        let anchors = self.anchors.iter().fold(8, |len, x| len + x.serialized_len());
+        self.url.serialized_len() + anchors + 1 + 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_decodes_and_strips_fragments_and_queries() {
+        assert_eq!(normalize("/wiki/g%C3%B6del%27s_theorem#Proof"), Some("/wiki/Gödel's_theorem".to_string()));
+        assert_eq!(normalize("/wiki/Sun?action=edit"), Some("/wiki/Sun".to_string()));
+        assert_eq!(normalize("/wiki/solar_system"), Some("/wiki/Solar_system".to_string()));
+        // An escaped # is part of the title.
+        assert_eq!(normalize("/wiki/C%23"), Some("/wiki/C#".to_string()));
+        // e followed by a combining acute accent is NFC normalized into é.
+        assert_eq!(normalize("/wiki/Caf%C3%A9"), normalize("/wiki/Cafe%CC%81"));
+        assert_eq!(normalize("/wiki/_Sun_"), Some("/wiki/Sun".to_string()));
+    }
+
+    #[test]
+    fn normalize_rejects_non_articles() {
+        assert_eq!(normalize("https://example.com/wiki/Sun"), None);
+        assert_eq!(normalize("/w/index.php?title=Sun"), None);
+        assert_eq!(normalize("/wiki/#top"), None);
+        assert_eq!(normalize("/wiki/File:Sun.png"), None);
+        assert_eq!(normalize("/wiki/user_talk:Someone"), None);
+        assert_eq!(normalize("/wiki/Category:Stars"), None);
+        assert_eq!(normalize("/wiki/%FF"), None);
+        // Only known namespaces are left out; other titles may contain a colon.
+        assert_eq!(normalize("/wiki/Star_Wars:_A_New_Hope"), Some("/wiki/Star_Wars:_A_New_Hope".to_string()));
+    }
+}
@@ -1,3 +1,12 @@
+// The crate is written in the style of the 2015 edition it targets: bare trait objects, try!, `ref` patterns,
+// `field: field` initializers, explicit returns and io::Error::new(ErrorKind::Other, ..).
+#![allow(bare_trait_objects, deprecated)]
+#![allow(clippy::redundant_field_names, clippy::redundant_static_lifetimes, clippy::needless_return,
+         clippy::legacy_numeric_constants, clippy::needless_borrowed_reference, clippy::needless_late_init,
+         clippy::question_mark, clippy::io_other_error)]
+// The graph, tree and queue modules are general purpose, and not every command uses all they offer.
+#![allow(dead_code)]
+
 /// External dependencies
 #[macro_use]
 extern crate lazy_static;
@@ -5,18 +14,15 @@ extern crate cfile_rs;
 extern crate random_access_file;
 
 extern crate select;
-use select::document::Document;
-use select::predicate::{Predicate, Attr, Class, Name};
 
 extern crate hyper;
 extern crate hyper_native_tls;
-use hyper::client;
-use hyper::client::*;
-use hyper::net::HttpsConnector;
-use hyper_native_tls::NativeTlsClient;
 
 extern crate regex;
-use regex::Regex;
+
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+extern crate rust_stemmers;
 
 extern crate iron;
 use iron::prelude::*;
@@ -41,7 +47,6 @@ mod log;
 mod persistable_hash;
 
 mod priority_queue;
-use priority_queue::PriorityQueue;
 
 mod frequency_table;
 
@@ -58,72 +63,293 @@ mod node;
 mod graph;
 use graph::AssociatedGraph;
 
+mod heuristic;
+use heuristic::Zero;
+
+mod landmarks;
+use landmarks::Landmarks;
+
+mod scores;
+use scores::Scores;
+
+mod page_source;
+use page_source::{ PageSource, HttpSource, DirectorySource };
+
+mod dump;
+
+mod crawl_state;
+use crawl_state::CrawlState;
+
+mod config;
+use config::{ Config, Command };
+
+mod politeness;
+
+mod content;
+
+mod term_dictionary;
+use term_dictionary::TermDictionary;
+
+mod tokenizer;
+use tokenizer::{ Tokenizer, TokenizerConfig };
+
+mod corpus;
+use corpus::CorpusIndex;
+
+mod similarity;
+
+mod minhash;
+use minhash::{ LshIndex, Signature };
+
+mod alias;
+use alias::AliasTable;
+
+mod link;
+use link::Link;
+
 use std::io;
-use std::io::{ Read, Write, SeekFrom };
-use std::collections::{ HashSet, BTreeSet, HashMap };
+use std::io::Write;
+use std::collections::{ HashSet, HashMap };
 use std::collections::vec_deque::VecDeque;
 use std::fs;
-use std::rc::Rc;
-use std::cell::RefCell;
 use std::env;
 use std::sync::{ Arc, Mutex, RwLock };
 use std::thread;
-use std::time::{ SystemTime, Duration };
+use std::time::{ SystemTime, Instant };
 
-/// The page to begin from.
-const SEED_PAGE: &'static str = "/wiki/Astronomy";
+/// Creates the source pages are fetched from: the local mirror if one was configured,
+/// otherwise the configured wiki.
+fn open_source(config: &Config) -> Arc<PageSource> {
+    match config.mirror {
+        Some(ref dir) => {
+            log!("Info", "Reading pages from local mirror \"{}\"", dir);
+            Arc::new(DirectorySource::new(dir))
+        },
+        None => match HttpSource::new(&config.base_url, &config.politeness) {
+            Ok(x) => Arc::new(x),
+            Err(e) => {
+                error!("FATAL: Failed to create http page source, encountered error \"{}\"", e);
+                panic!("");
+            }
+        }
+    }
+}
 
-/// The number of pages to be loaded and stuck into the graph.
-const NUM_PAGES: i32 = 50000;
+/// Opens the tree of urls that couldn't be fetched (url -> why), which lives across runs until cleaned.
+fn open_failed(config: &Config) -> Option<PTree<String, String>> {
+    match PTree::<String, String>::open_or_new(&config.path("failed")) {
+        Ok(x) => Some(x),
+        Err(e) => {
+            error!("Failed to open the failed url tree, failures won't be recorded. {}", e);
+            None
+        }
+    }
+}
 
-const NUM_THREADS: i32 = 32;
+/// Logs that url couldn't be fetched and remembers it in the failed tree.
+fn record_failure(failed: &mut Option<PTree<String, String>>, url: &str, e: &io::Error) {
+    error!("Failed to fetch page \"{}\", encountered error \"{}\"", url, e);
+    if let Some(ref mut failed) = *failed {
+        if let Err(e) = failed.insert(&url.to_string(), &format!("{}", e)) {
+            error!("Failed to record failed url, encountered error \"{}\"", e);
+        }
+    }
+}
 
-fn load_pages(n: i32) {
+/// Writes a checkpoint of the crawl to disk. The link tree is flushed while set_max is locked so
+/// the checkpoint never refers to links that haven't made it to disk, and the term dictionary is saved
+/// before the cache so the cache never refers to term ids that haven't.
+#[allow(clippy::too_many_arguments)]
+fn checkpoint(to: &str,
+              terms_path: &str,
+              tokenizer: &Tokenizer,
+              cache: &RwLock<PTree<String, Page>>,
+              aliases: &RwLock<AliasTable>,
+              visited: &RwLock<PTree<String, u8>>,
+              links: &RwLock<PTree<u64, String>>,
+              set_ind: &RwLock<u64>,
+              set_max: &RwLock<u64>,
+              dld_pages: &RwLock<i32>,
+              count: &RwLock<i32>,
+              in_flight: &RwLock<HashMap<i32, String>>,
+              resumed: &Mutex<Vec<String>>) -> Result<(), io::Error> {
+    check!(tokenizer.terms().persist(terms_path));
+    check!(cache.write().unwrap().flush());
+    check!(aliases.write().unwrap().flush());
+    check!(visited.write().unwrap().flush());
+    let mut state = CrawlState::new();
+    {
+        let set_max = set_max.read().unwrap();
+        check!(links.write().unwrap().flush());
+        state.set_max = *set_max;
+    }
+    {
+        // Threads take urls under these locks, so every url is either past set_ind, waiting to be resumed or
+        // in flight.
+        let resumed = resumed.lock().unwrap();
+        let set_ind = set_ind.read().unwrap();
+        state.set_ind = *set_ind;
+        state.in_flight = in_flight.read().unwrap().values().cloned().collect();
+        state.in_flight.extend(resumed.iter().cloned());
+    }
+    state.downloaded = *dld_pages.read().unwrap() as u64;
+    state.visited = *count.read().unwrap() as u64;
+    state.persist(to)
+}
+
+/// Sets up the tokenizer pages in the cache are (and will be) tokenized with. The settings the cache was
+/// built with are kept next to it, and win over the configured ones so pages stay comparable.
+fn open_tokenizer(config: &Config) -> Result<Tokenizer, io::Error> {
+    let path = config.path("tokenizer");
+    let settings = match TokenizerConfig::from_disk(&path) {
+        Ok(stored) => {
+            if stored != config.tokenizer {
+                error!("The cache was tokenized with {:?}, using those settings instead of {:?}. Run clean to change them.",
+                       stored, config.tokenizer);
+            }
+            stored
+        },
+        Err(_) => {
+            check!(config.tokenizer.persist(&path));
+            config.tokenizer.clone()
+        }
+    };
+    let terms = match TermDictionary::from_disk(&config.path("terms")) {
+        Ok(terms) => terms,
+        Err(e) => {
+            // The word counts of cached pages refer to term ids in the dictionary, so starting a new one would
+            // give their ids to other terms.
+            let cached = match PTree::<String, Page>::open(&config.path("cache")) {
+                Ok(mut cache) => match cache.is_empty() {
+                    Ok(empty) => !empty,
+                    Err(_) => true
+                },
+                Err(_) => false
+            };
+            if cached {
+                return Err(io::Error::new(e.kind(), format!("the cache's term dictionary can't be read ({}), run clean to start over", e)));
+            }
+            TermDictionary::new()
+        }
+    };
+    Tokenizer::with_terms(settings, terms)
+}
+
+/// Saves the ids the tokenizer gave terms, which the word counts of pages in the cache refer to.
+fn save_terms(config: &Config, tokenizer: &Tokenizer) -> Result<(), io::Error> {
+    tokenizer.terms().persist(&config.path("terms"))
+}
+
+fn load_pages(config: &Config, source: Arc<PageSource>) {
+    let n = config.max_pages;
+    let resume = config.resume;
+    let max_depth = config.max_depth;
+    let state_path = config.path("crawl_state");
+    let terms_path = config.path("terms");
+    let tokenizer = match open_tokenizer(config) {
+        Ok(x) => Arc::new(x),
+        Err(e) => {
+            error!("Failed to set up the tokenizer, {}", e);
+            return;
+        }
+    };
     log!("Info", "Creating cache...");
-    let mut cache;
-    match PTree::<String, Page>::open("data/cache") {
+    let cache;
+    match PTree::<String, Page>::open_or_new(&config.path("cache")) {
         Ok(x) => cache = Arc::new(RwLock::new(x)),
         Err(e) => {
             error!("Failed to open cache, {}", e);
             return;
         }
     };
+    let aliases;
+    match AliasTable::open(&config.path("aliases")) {
+        Ok(x) => aliases = Arc::new(RwLock::new(x)),
+        Err(e) => {
+            error!("Failed to open alias table, {}", e);
+            return;
+        }
+    }
+    let mut state = CrawlState::new();
+    if resume {
+        log!("Info", "Resuming crawl from checkpoint...");
+        match CrawlState::from_disk(&state_path) {
+            Ok(x) => state = x,
+            Err(e) => {
+                error!("Failed to read crawl checkpoint, {}", e);
+                return;
+            }
+        }
+        log!("Info", "Resuming after {} pages ({} downloaded), {} urls left in the frontier",
+             state.visited, state.downloaded, state.set_max - state.set_ind.min(state.set_max));
+    }
     log!("Info", "Creating cache-set...");
-    let mut visited;
-    match PTree::<String, u8>::new("data/temp_visited") {
+    let visited;
+    let path = config.path("temp_visited");
+    let result = if resume { PTree::<String, u8>::open(&path) } else { PTree::<String, u8>::new(&path) };
+    match result {
         Ok(x) => visited = Arc::new(RwLock::new(x)),
         Err(e) => {
             error!("Failed to create visited PTree, {}", e);
             return;
         }
     }
-    let mut links;
-    match PTree::<u64, String>::new("data/temp_link") {
+    let links;
+    let path = config.path("temp_link");
+    let result = if resume { PTree::<u64, String>::open(&path) } else { PTree::<u64, String>::new(&path) };
+    match result {
         Ok(x) => links = Arc::new(RwLock::new(x)),
         Err(e) => {
             error!("Failed to create temp_link PTree, {}", e);
             return;
         }
     }
+    let failed = Arc::new(Mutex::new(open_failed(config)));
+    // The number of clicks from a seed each url in the frontier is, only needed if the depth is limited.
+    let mut depths = None;
+    if max_depth.is_some() {
+        let path = config.path("temp_depth");
+        let result = if resume { PTree::<String, u64>::open(&path) } else { PTree::<String, u64>::new(&path) };
+        match result {
+            Ok(x) => depths = Some(Arc::new(RwLock::new(x))),
+            Err(e) => {
+                error!("Failed to create temp_depth PTree, {}", e);
+                return;
+            }
+        }
+    }
+    if !resume {
+        for seed in config.seeds().iter() {
+            state.set_max += 1;
+            if let Err(e) = links.write().unwrap().insert(&state.set_max, seed) {
+                error!("Failed to add seed \"{}\" to the links tree, encountered error \"{}\"", seed, e);
+                return;
+            }
+        }
+        state.set_ind = 1;
+    }
 
-    let WIKIPEDIA_DOMAIN: String = "https://en.wikipedia.org".to_string();
     // Number of pages downloaded
-    let mut dld_pages = Arc::new(RwLock::new(0));
+    let dld_pages = Arc::new(RwLock::new(state.downloaded as i32));
     // index in links to find the next url
-    let mut set_ind = Arc::new(RwLock::new(0));
+    let set_ind = Arc::new(RwLock::new(state.set_ind));
     // the number of urls total, if set_ind >= set_max then there are no more urls
-    let mut set_max = Arc::new(RwLock::new(0));
+    let set_max = Arc::new(RwLock::new(state.set_max));
+    // the url each thread is currently working on, so they aren't lost if the crawl is interrupted, and
+    // so threads waiting on an empty frontier know whether any more urls can be added to it
+    let in_flight = Arc::new(RwLock::new(HashMap::new()));
+    // urls that were being worked on when the previous crawl was interrupted
+    let resumed = Arc::new(Mutex::new(state.in_flight));
     // a vector containing all of the handles to all of the threads
     let mut handles = vec![];
 
-    let ssl = NativeTlsClient::new().unwrap();
-    let connector = HttpsConnector::new(ssl);
-    let client = Arc::new(Client::with_connector(connector));
     let done = Arc::new(Mutex::new(false));
-    let count = Arc::new(RwLock::new(0));
+    let count = Arc::new(RwLock::new(state.visited as i32));
 
-    for i in 0..NUM_THREADS {
-        let client = client.clone();
+    for i in 0..config.threads {
+        let source = source.clone();
+        let content = config.content.clone();
+        let tokenizer = tokenizer.clone();
         let visited = visited.clone();
         let links = links.clone();
         let cache = cache.clone();
@@ -131,98 +357,203 @@ fn load_pages(n: i32) {
         let set_max = set_max.clone();
         let dld_pages = dld_pages.clone();
         let count = count.clone();
-        let mut page_url = SEED_PAGE.to_string();
-        let WIKIPEDIA_DOMAIN = WIKIPEDIA_DOMAIN.to_string();
+        let in_flight = in_flight.clone();
+        let resumed = resumed.clone();
+        let terms_path = terms_path.clone();
+        let depths = depths.clone();
+        let failed = failed.clone();
+        let aliases = aliases.clone();
         handles.push(thread::spawn(move || {
             thread::sleep_ms(100);
             let new_page = || -> Option<String> {
                 thread::sleep_ms(5);
-                let mut ret = String::new();
+                // Whatever this thread was working on is done, and its links are in the frontier.
+                in_flight.write().unwrap().remove(&i);
                 loop {
-                    match links.write().unwrap().search(&*set_ind.read().unwrap()) {
-                        Ok(Some(e)) => ret = e,
-                        Ok(None) => {
-                            if *dld_pages.read().unwrap() >= n { return None }
-                            continue
-                        }
-                        Err(e) => {
-                            error!("Failed to read from links tree, encountered error \"{}\"", e);
-                            return None
+                    // A url is put in flight before the lock it was taken under is let go, so a checkpoint
+                    // always finds it in one place or the other.
+                    let resumed_url = {
+                        let mut resumed = resumed.lock().unwrap();
+                        let url = resumed.pop();
+                        if let Some(ref url) = url { in_flight.write().unwrap().insert(i, url.clone()); }
+                        url
+                    };
+                    let ret = if let Some(url) = resumed_url {
+                        url
+                    } else {
+                        // set_ind stays locked until it's moved past the url, so no two threads take the same one.
+                        let result = {
+                            let mut ind = set_ind.write().unwrap();
+                            let result = links.write().unwrap().search(&*ind);
+                            if let Ok(Some(ref url)) = result {
+                                *ind += 1;
+                                in_flight.write().unwrap().insert(i, url.clone());
+                            }
+                            result
+                        };
+                        match result {
+                            Ok(Some(e)) => e,
+                            Ok(None) => {
+                                if *dld_pages.read().unwrap() >= n { return None }
+                                // The frontier is empty, but a thread working on a page may still add to it.
+                                // Once none are, no more urls are coming. Links are pushed before a thread
+                                // stops working, so the frontier is checked again after seeing that.
+                                if in_flight.read().unwrap().is_empty() && *set_ind.read().unwrap() > *set_max.read().unwrap() {
+                                    return None
+                                }
+                                thread::sleep_ms(50);
+                                continue
+                            }
+                            Err(e) => {
+                                error!("Failed to read from links tree, encountered error \"{}\"", e);
+                                return None
+                            }
                         }
-                    }
+                    };
                     let result = {
                         visited.write().unwrap().contains_key(&ret)
                     };
                     if let Ok(x) = result {
-                        *set_ind.write().unwrap() += 1;
-                        if x { continue }
+                        if x {
+                            in_flight.write().unwrap().remove(&i);
+                            continue
+                        }
                         else { return Some(ret) }
                     } else if let Err(e) = result {
                         error!("Failed to read from visited tree, error \"{}\"", e);
                         return None
                     }
                 }
-                return Some(ret)
+            };
+            // Pushes the links of a page depth clicks from a seed onto the frontier.
+            let push_links = |page_links: Vec<Link>, depth: u64| {
+                if let Some(max) = max_depth {
+                    if depth >= max { return }
+                }
+                for link in page_links.into_iter() {
+                    let link = aliases.write().unwrap().resolve(&link.url);
+                    let result = {
+                        visited.write().unwrap().contains_key(&link)
+                    };
+                    match result {
+                        Ok(x) => if x { continue },
+                        Err(e) => {
+                            error!("Error reading from visited tree, \"{}\"", e);
+                            continue;
+                        }
+                    }
+                    if let Some(ref depths) = depths {
+                        let mut depths = depths.write().unwrap();
+                        if let Ok(false) = depths.contains_key(&link) {
+                            if let Err(e) = depths.insert(&link, &(depth + 1)) {
+                                error!("Failed to write to depth tree, encountered error \"{}\"", e);
+                            }
+                        }
+                    }
+                    let mut x = set_max.write().unwrap();
+                    *x += 1;
+                    if let Err(e) = links.write().unwrap().insert(&*x, &link) {
+                        error!("Failed to write to links tree, encountered error \"{}\"", e);
+                    }
+                }
             };
 
-            if i != 0 {  *set_ind.write().unwrap() += 1; page_url = new_page().unwrap(); }
+            let mut page_url = match new_page() {
+                Some(x) => x,
+                None => return
+            };
 
             while *dld_pages.read().unwrap() < n + 1 {
+                let canonical = aliases.write().unwrap().resolve(&page_url);
+                if canonical != page_url {
+                    if let Err(e) = visited.write().unwrap().insert(&page_url, &0) {
+                        error!("Failed to write to visited tree, encountered error \"{}\"", e);
+                    }
+                    let seen = visited.write().unwrap().contains_key(&canonical);
+                    if let Ok(true) = seen {
+                        match new_page() {
+                            Some(x) => { page_url = x; continue },
+                            None => break
+                        }
+                    }
+                    page_url = canonical;
+                }
+                let depth = match depths {
+                    Some(ref depths) => match depths.write().unwrap().search(&page_url) {
+                        Ok(Some(x)) => x,
+                        _ => 0
+                    },
+                    None => 0
+                };
                 let result = {
                     let mut cache = cache.write().unwrap();
                     cache.search(&page_url)
                 };
                 match result {
                     Ok(Some(x)) => {
-                        for link in x.links.into_iter() {
-                            let result = {
-                                visited.write().unwrap().contains_key(&link)
-                            };
-                            match result {
-                                Ok(x) => if x { continue },
-                                Err(e) => {
-                                    error!("Error reading from visited tree, \"{}\"", e);
-                                    continue;
-                                }
-                            }
-                            let mut x = set_max.write().unwrap();
-                            *x += 1;
-                            links.write().unwrap().insert(&*x, &link);
+                        push_links(x.links, depth);
+                        if let Err(e) = visited.write().unwrap().insert(&page_url, &0) {
+                            error!("Failed to write to visited tree, encountered error \"{}\"", e);
                         }
-                        visited.write().unwrap().insert(&page_url, &0);
                         //print!("@");
                         *count.write().unwrap() += 1;
-                        let result = new_page();
                         if let Some(x) = new_page() {
                             page_url = x;
                         } else {
                             break;
                         }
                     },
-                    result @ _ => {
+                    result => {
                         if let Err(e) = result {
                             error!("Encountered error \"{}\" while reading from cache.", e);
                         }
-                        let mut resp = client.get(&(WIKIPEDIA_DOMAIN.clone() + page_url.as_ref())).send().unwrap();
-                        let mut html = String::new();
-                        resp.read_to_string(&mut html);
+                        let html = match source.fetch(page_url.as_ref()) {
+                            Ok(html) => html,
+                            Err(e) => {
+                                record_failure(&mut failed.lock().unwrap(), &page_url, &e);
+                                // Failures count towards max_pages too, or a crawl of pages that can't be
+                                // fetched would never end.
+                                *dld_pages.write().unwrap() += 1;
+                                if let Err(e) = visited.write().unwrap().insert(&page_url, &0) {
+                                    error!("Failed to write to visited tree, encountered error \"{}\"", e);
+                                }
+                                match new_page() {
+                                    Some(x) => { page_url = x; continue },
+                                    None => break
+                                }
+                            }
+                        };
                         *dld_pages.write().unwrap() += 1;
-                        let mut page = Page::new(page_url.as_ref(), html.as_ref());
-                        cache.write().unwrap().insert(&page_url, &page);
-                        for link in page.links.into_iter() {
-                            match visited.write().unwrap().contains_key(&link) {
-                                Ok(x) => if x { continue },
-                                Err(e) => {
-                                    error!("Error reading from visited tree, \"{}\"", e);
-                                    continue;
+                        let page = Page::new(page_url.as_ref(), html.as_ref(), &content, &tokenizer);
+                        if page.url != page_url {
+                            // page_url redirected to page.url
+                            if let Err(e) = aliases.write().unwrap().insert(&page_url, &page.url) {
+                                error!("Failed to record alias \"{}\", encountered error \"{}\"", page_url, e);
+                            }
+                            if let Err(e) = visited.write().unwrap().insert(&page_url, &0) {
+                                error!("Failed to write to visited tree, encountered error \"{}\"", e);
+                            }
+                            let seen = visited.write().unwrap().contains_key(&page.url);
+                            if let Ok(true) = seen {
+                                match new_page() {
+                                    Some(x) => { page_url = x; continue },
+                                    None => break
                                 }
                             }
-                            let mut x = set_max.write().unwrap();
-                            *x += 1;
-                            links.write().unwrap().insert(&*x, &link);
+                            page_url = page.url.clone();
+                        }
+                        // The page's word counts refer to term ids, which have to be saved before it is.
+                        match tokenizer.terms().persist(&terms_path) {
+                            Ok(()) => if let Err(e) = cache.write().unwrap().insert_or_replace(&page_url, &page) {
+                                error!("Failed to cache page, encountered error \"{}\"", e);
+                            },
+                            Err(e) => error!("Failed to save the term dictionary, encountered error \"{}\"", e)
                         }
+                        push_links(page.links, depth);
                         //print!("#");
-                        visited.write().unwrap().insert(&page_url, &0);
+                        if let Err(e) = visited.write().unwrap().insert(&page_url, &0) {
+                            error!("Failed to write to visited tree, encountered error \"{}\"", e);
+                        }
                         *count.write().unwrap() += 1;
                     },
                 }
@@ -233,6 +564,18 @@ fn load_pages(n: i32) {
     let handle = {
         let done = done.clone();
         let count = count.clone();
+        let cache = cache.clone();
+        let aliases = aliases.clone();
+        let visited = visited.clone();
+        let links = links.clone();
+        let set_ind = set_ind.clone();
+        let set_max = set_max.clone();
+        let dld_pages = dld_pages.clone();
+        let in_flight = in_flight.clone();
+        let resumed = resumed.clone();
+        let state_path = state_path.clone();
+        let terms_path = terms_path.clone();
+        let tokenizer = tokenizer.clone();
         thread::spawn(move || {
             let mut time = SystemTime::now();
             let mut i = 0;
@@ -241,39 +584,54 @@ fn load_pages(n: i32) {
                 if *done.lock().unwrap() { break }
                 thread::sleep_ms(500);
                 i += 1;
-                match time.elapsed() {
-                    Ok(elapsed) => {
-                        let t = (elapsed.subsec_nanos() / 1000000) as u64;
-                        let read = *count.read().unwrap();
-                        print!("{}[2K\r\t<{} Pages / sec> <Page {}>", std::char::from_u32(27).unwrap(), (read - last_count) as f64 / (t as f64 / 1000.0), read);
-                        io::stdout().flush().unwrap();
-                        for i in 0..(i % 3) { print!(".") }
-                        last_count = read;
-                        //*count.write().unwrap() = 0;
-                        time = SystemTime::now();
-                    },
-                    Err(e) => {}
+                // Checkpoint roughly every 10 seconds
+                if i % 20 == 0 {
+                    if let Err(e) = checkpoint(&state_path, &terms_path, &tokenizer, &cache, &aliases, &visited, &links, &set_ind, &set_max, &dld_pages, &count, &in_flight, &resumed) {
+                        error!("Failed to checkpoint crawl, encountered error \"{}\"", e);
+                    }
+                }
+                if let Ok(elapsed) = time.elapsed() {
+                    let t = elapsed.subsec_millis() as u64;
+                    let read = *count.read().unwrap();
+                    print!("{}[2K\r\t<{} Pages / sec> <Page {}>", std::char::from_u32(27).unwrap(), (read - last_count) as f64 / (t as f64 / 1000.0), read);
+                    io::stdout().flush().unwrap();
+                    for _ in 0..(i % 3) { print!(".") }
+                    last_count = read;
+                    //*count.write().unwrap() = 0;
+                    time = SystemTime::now();
                 }
             }
         })
 
     };
     for x in handles.into_iter() {
-        x.join();
+        if x.join().is_err() {
+            error!("A crawling thread panicked");
+        }
     }
     *done.lock().unwrap() = true;
-    //handle.join();
+    if let Err(e) = checkpoint(&state_path, &terms_path, &tokenizer, &cache, &aliases, &visited, &links, &set_ind, &set_max, &dld_pages, &count, &in_flight, &resumed) {
+        error!("Failed to checkpoint crawl, encountered error \"{}\"", e);
+    }
+    if handle.join().is_err() {
+        error!("The progress thread panicked");
+    }
 }
 
-fn create_graph(n: i32) {
-    let ssl = NativeTlsClient::new().unwrap();
-    let connector = HttpsConnector::new(ssl);
-    let client = Client::with_connector(connector);
-    let mut cache: PTree<String, Page> = match PTree::<String, Page>::open("data/cache") {
+fn create_graph(config: &Config, source: &PageSource) {
+    let tokenizer = match open_tokenizer(config) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("FATAL: Failed to set up the tokenizer, encountered error \"{}\"", e);
+            panic!("");
+        }
+    };
+    let path = config.path("cache");
+    let mut cache: PTree<String, Page> = match PTree::<String, Page>::open(&path) {
         Ok(x) => x,
         Err(e) => {
             error!("Failed to open cache, attempting to create a new one. {}", e);
-            match PTree::<String, Page>::new("data/cache") {
+            match PTree::<String, Page>::new(&path) {
                 Ok(x) => x,
                 Err(e) => {
                     error!("FATAL: Failed to create new cache! Encountered error \"{}\"", e);
@@ -283,69 +641,131 @@ fn create_graph(n: i32) {
         }
     };
 
-    let WIKIPEDIA_DOMAIN: String = "https://en.wikipedia.org".to_string();
+    let mut alias_table = match AliasTable::open(&config.path("aliases")) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("FATAL: Failed to open alias table! Encountered error \"{}\"", e);
+            panic!("");
+        }
+    };
+    let mut aliases = match alias_table.all() {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to read alias table, encountered error \"{}\"", e);
+            HashMap::new()
+        }
+    };
+    let mut failed = open_failed(config);
+    // Urls that couldn't be fetched this run, so they aren't retried each time they're linked to.
+    let mut failed_urls = HashSet::new();
     let mut graph = AssociatedGraph::new();
-    let mut page_count = 1;
-    let mut page_url = SEED_PAGE.to_string();
-    let mut links = VecDeque::new();
+    // Urls to visit, along with how many clicks they are from a seed
+    let mut links: VecDeque<(String, u64)> = config.seeds().into_iter().map(|x| (x, 0)).collect();
     let mut pages = HashMap::new();
 
-    while page_count < n + 1 {
-        page_count += 1;
-        match cache.search(&page_url) {
+    while pages.len() < config.max_pages as usize {
+        let (page_url, depth) = match links.pop_front() {
+            Some(x) => x,
+            None => {
+                log!("Info", "Ran out of links after {} pages", pages.len());
+                break;
+            }
+        };
+        let page_url = aliases.get(&page_url).cloned().unwrap_or(page_url);
+        if pages.contains_key(&page_url) || failed_urls.contains(&page_url) { continue }
+        let page = match cache.search(&page_url) {
             Ok(Some(x)) => {
-                let page = x;
                 log!("Info", "Found page \"{}\" in cache", page_url);
-                links.append(&mut page.links.iter().map(|x| x.clone()).collect());
-                pages.insert(page_url.clone(), page);
-                loop {
-                    page_url = links.pop_front().unwrap();
-                    if pages.contains_key(&page_url) {
-                        continue
-                    } else {
-                        break
-                    }
-                }
+                x
             },
-            result @ _ => {
+            result => {
                 if let Err(e) = result {
                     error!("Encountered error \"{}\" while reading from cache.", e);
                 }
-                log!("Info", "Requesting page '{}{}'", WIKIPEDIA_DOMAIN, page_url);
-                let mut resp = client.get(&(WIKIPEDIA_DOMAIN.clone() + page_url.as_ref())).send().unwrap();
-                let mut html = String::new();
-                resp.read_to_string(&mut html);
-
-                let mut page = Page::new(page_url.as_ref(), html.as_ref());
+                log!("Info", "Requesting page '{}'", page_url);
+                let html = match source.fetch(page_url.as_ref()) {
+                    Ok(html) => html,
+                    Err(e) => {
+                        record_failure(&mut failed, &page_url, &e);
+                        failed_urls.insert(page_url);
+                        continue;
+                    }
+                };
 
-                links.append(&mut page.links.iter().map(|x| x.clone()).collect());
-                match cache.insert(&page_url, &page) {
+                let page = Page::new(page_url.as_ref(), html.as_ref(), &config.content, &tokenizer);
+                if page.url != page_url {
+                    // page_url redirected to page.url
+                    if let Err(e) = alias_table.insert(&page_url, &page.url) {
+                        error!("Failed to record alias \"{}\", encountered error \"{}\"", page_url, e);
+                    }
+                    aliases.insert(page_url.clone(), page.url.clone());
+                    if pages.contains_key(&page.url) { continue }
+                }
+                // The page's word counts refer to term ids, which have to be saved before it is.
+                match save_terms(config, &tokenizer).and_then(|_| cache.insert_or_replace(&page.url, &page)) {
                     Ok(()) => {},
                     Err(e) => error!("Failed to cache page, encountered error \"{}\"", e)
                 }
-                pages.insert(page_url.clone(), page);
-                loop {
-                    page_url = links.pop_front().unwrap();
-                    if pages.contains_key(&page_url) {
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
+                page
             },
+        };
+        if config.skip_disambiguation && page.disambiguation {
+            log!("Info", "Skipping disambiguation page \"{}\"", page.url);
+            continue;
+        }
+        if config.max_depth.is_none_or(|max| depth < max) {
+            links.extend(page.links.iter().map(|x| (x.url.clone(), depth + 1)));
         }
+        pages.insert(page.url.clone(), page);
+    }
+    if let Err(e) = save_terms(config, &tokenizer) {
+        error!("Failed to save the term dictionary, encountered error \"{}\"", e);
     }
 
+    let corpus = if similarity::needs_corpus(&config.metric) {
+        log!("Info", "Indexing the cache for the {} metric...", config.metric);
+        match CorpusIndex::from_cache(&mut cache) {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to index the cache, encountered error \"{}\"", e);
+                return;
+            }
+        }
+    } else {
+        CorpusIndex::new()
+    };
+    let metric = match similarity::metric_by_name(&config.metric, corpus, pages.values(), |url| aliases.get(url).cloned().unwrap_or(url.to_string())) {
+        Some(x) => x,
+        None => {
+            error!("FATAL: Unknown similarity metric \"{}\"", config.metric);
+            return;
+        }
+    };
+    graph.set_metric(metric.name().to_string());
+    // add only fails when the node is already in the graph, which is fine here.
     for page in pages.values() {
-        graph.add(page.url.clone());
+        let _ = graph.add(page.url.clone());
+        // Two links can lead to the same page through aliases, only the first gets an edge.
+        let mut connected = HashSet::new();
+        let mut targets = vec![];
         for link in page.links.iter() {
-            if pages.contains_key(link) {
-                graph.add(link.to_string());
-                graph.connect(&page.url, link, 1.1 - page.word_freq.compare(&pages[link].word_freq));
+            let url = aliases.get(&link.url).unwrap_or(&link.url);
+            if pages.contains_key(url) && connected.insert(url) {
+                targets.push((link, &pages[url]));
             }
         }
+        let weights = metric.weights(page, &targets);
+        for (&(_, to), weight) in targets.iter().zip(weights) {
+            let _ = graph.add(to.url.clone());
+            graph.connect(&page.url, &to.url, weight);
+        }
     }
-    match graph.persist("data/pers") {
+    for (alias, canonical) in aliases.iter() {
+        if pages.contains_key(canonical) {
+            graph.alias(alias.clone(), canonical);
+        }
+    }
+    match graph.persist(&config.path("pers")) {
         Ok(()) => log!("Log", "Created persistant graph."),
         Err(e) => {
             error!("Failed to persist graph, encountered error \"{}\"", e);
@@ -354,29 +774,80 @@ fn create_graph(n: i32) {
     }
 }
 
-fn load_graph() -> Result<AssociatedGraph<String>, io::Error> {
-    match AssociatedGraph::<String>::from_disk("data/pers") {
+/// Imports up to n articles from a pages-articles.xml dump into the cache, optionally taking
+/// their links from a pagelinks.sql dump.
+fn import_dump(config: &Config, xml: &str) {
+    log!("Info", "Importing pages from dump \"{}\"...", xml);
+    let tokenizer = match open_tokenizer(config) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("FATAL: Failed to set up the tokenizer, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let pagelinks = config.pagelinks.as_deref();
+    match dump::import(xml, pagelinks, &config.path("cache"), &config.path("aliases"), &config.path("terms"), config.max_pages as usize, &tokenizer) {
+        Ok(count) => log!("Info", "Imported {} pages into the cache.", count),
+        Err(e) => error!("Failed to import dump, encountered error \"{}\"", e)
+    }
+    // Pages imported before an error are in the cache, so the terms are saved either way.
+    if let Err(e) = save_terms(config, &tokenizer) {
+        error!("Failed to save the term dictionary, encountered error \"{}\"", e);
+    }
+}
+
+fn load_graph(config: &Config, source: &PageSource) -> Result<AssociatedGraph<String>, io::Error> {
+    match AssociatedGraph::<String>::from_disk(&config.path("pers")) {
         Ok(graph) => return Ok(graph),
-        Err(e)    => error!("Failed to load persisted graph, attempting to create new graph.")
+        Err(_)    => error!("Failed to load persisted graph, attempting to create new graph.")
     }
-    create_graph(NUM_PAGES);
-    match AssociatedGraph::<String>::from_disk("data/pers") {
+    create_graph(config, source);
+    match AssociatedGraph::<String>::from_disk(&config.path("pers")) {
         Ok(graph) => return Ok(graph),
         Err(e)    => error!("FATAL: Failed to load persisted graph even after creation. Encountered error \"{}\"", e)
     }
     panic!("");
 }
 
-fn server() {
+/// Quotes and escapes s as a json string.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// How many paths /paths returns if it isn't asked for a number.
+const DEFAULT_PATHS: usize = 3;
+
+/// The most paths /paths and /hops return.
+const MAX_PATHS: usize = 10;
+
+fn server(config: &Config, source: &PageSource) {
     log!("Log", "Attempting to load graph");
-    let graph = match load_graph() {
+    let graph = match load_graph(config, source) {
         Ok(graph) => graph,
         Err(e) => {
             error!("FATAL: Failed to load graph, encountered error \"{}\"", e);
             panic!("");
         }
     };
-    log!("Log", "Successfully loaded graph with {} nodes", graph.len());
+    log!("Log", "Successfully loaded graph with {} nodes, weighted by the {} metric", graph.len(), graph.metric());
+    let landmarks = open_landmarks(config, &graph);
+    if let Some(ref landmarks) = landmarks {
+        log!("Log", "Finding paths with {} landmarks", landmarks.len());
+    }
+    let pagerank = open_pagerank(config, &graph);
+    let (damping, tolerance, top) = (config.damping, config.tolerance, config.top);
 
     let mut list1 = "        <select id=\"list1\">\n".to_string();
     let mut list2 = "        <select id=\"list2\">\n".to_string();
@@ -401,35 +872,40 @@ fn server() {
     let whole_script = Arc::new(RwLock::new(include_str!("../js/whole_graph_script").to_string()));
     let graph = Arc::new(RwLock::new(graph));
     let graph_clone = graph.clone();
+    let page_graph = graph.clone();
+    let paths_graph = graph.clone();
+    let hops_graph = graph.clone();
+    let top_graph = graph.clone();
+    let related_graph = graph.clone();
+    let hits_graph = graph.clone();
 
     let mut router = Router::new();
-    router.get("/", move |r: &mut iron::Request| {
+    router.get("/", move |_: &mut iron::Request| {
         log!("Server", "serving / ...");
         let mut resp = iron::Response::with((Status::Ok, page.read().unwrap().clone()));
         resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Html, vec![])));
         Ok(resp)
     }, "index");
-    router.get("/graph", move |r: &mut iron::Request| {
+    router.get("/graph", move |_: &mut iron::Request| {
         log!("Server", "serving /graph ...");
         let mut resp = iron::Response::with((Status::Ok, whole_page.read().unwrap().clone()));
         resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Html, vec![])));
         Ok(resp)
     }, "graph");
-    router.get("/script.js", move |r: &mut iron::Request| {
+    router.get("/script.js", move |_: &mut iron::Request| {
         log!("Server", "serving /script.js ...");
         let mut resp = iron::Response::with((Status::Ok, script.read().unwrap().clone()));
         resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Javascript, vec![])));
         Ok(resp)
     }, "script.js");
-    router.get("/whole_graph_script.js", move |r: &mut iron::Request| {
+    router.get("/whole_graph_script.js", move |_: &mut iron::Request| {
         log!("Server", "serving /script.js ...");
         let mut resp = iron::Response::with((Status::Ok, whole_script.read().unwrap().clone()));
         resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Javascript, vec![])));
         Ok(resp)
     }, "whole_script.js");
-    router.post("/network", move |r: &mut iron::Request| {
+    router.post("/network", move |_: &mut iron::Request| {
         log!("Server", "serving /network ...");
-        let map = r.get_ref::<Params>().unwrap();
 
         let path = graph_clone.read().unwrap().keys();
         let mut json_nodes = "[\n".to_string();
@@ -438,8 +914,7 @@ fn server() {
         json_edges.reserve(path.len() * 256);
 
         let num_nodes = path.len();
-        let mut cid = 0;
-        for node in path {
+        for (cid, node) in path.into_iter().enumerate() {
             let id = graph_clone.read().unwrap().get_id(&node).unwrap();
             json_nodes += format!("    {{ \"id\": {}, \"label\": \"{}\", \"cid\": {}, \"group\": {}}}\n,",
                                id,
@@ -450,7 +925,6 @@ fn server() {
                 let to = graph_clone.read().unwrap().get_id(&connection).unwrap();
                 json_edges += format!("    {{ \"from\": {}, \"to\": {}, \"arrows\": \"to\" }}\n,", id, to).as_ref();
             }
-            cid += 1;
         }
         let _ = json_nodes.pop();
         let _ = json_edges.pop();
@@ -479,10 +953,20 @@ fn server() {
             _ => return Ok(iron::Response::with(iron::status::NotFound))
         }
 
+        // mode=hops finds the path with the fewest clicks instead of the lightest one.
+        let hops = match map.find(&["mode"]) {
+            Some(&Value::String(ref mode)) => mode == "hops",
+            _ => false
+        };
+
         let path;
         {
-            let mut graph = graph.write().unwrap();
-            let p = graph.shortest_path(&to, &from);
+            let graph = graph.read().unwrap();
+            let p = match landmarks {
+                _ if hops => graph.hop_path(&from, &to),
+                Some(ref landmarks) => graph.astar(&from, &to, landmarks),
+                None => graph.shortest_path(&from, &to)
+            };
             if p.is_none() { return Ok(iron::Response::with(iron::status::NotFound)) }
             path = p.unwrap();
         }
@@ -494,10 +978,10 @@ fn server() {
         let mut y = 0;
         let mut x = 0;
         let mut last_id = 0x12345678;
-        for node in path {
-            let id = graph.read().unwrap().get_id(&node).unwrap();
+        for node in path.nodes() {
+            let id = graph.read().unwrap().get_id(node).unwrap();
             json_nodes += format!("    {{ \"id\": {}, \"label\": \"{}\", \"x\": {}, \"y\": {} }}\n,",
-                               graph.read().unwrap().get_id(&node).unwrap(),
+                               id,
                                node.as_ref(),
                                x,
                                y).as_ref();
@@ -520,60 +1004,554 @@ fn server() {
         Ok(resp)
     }, "path");
 
-    Iron::new(router).http("localhost:1243").unwrap();
+    router.post("/paths", move |r: &mut iron::Request| {
+        log!("Server", "serving /paths ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        let (from, to) = match (map.find(&["from"]), map.find(&["to"])) {
+            (Some(&Value::String(ref f)), Some(&Value::String(ref t))) => (f.clone(), t.clone()),
+            _ => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+        let k = match map.find(&["k"]) {
+            Some(&Value::String(ref k)) => match k.parse::<usize>() {
+                Ok(k) => k.min(MAX_PATHS),
+                Err(_) => return Ok(iron::Response::with(iron::status::BadRequest))
+            },
+            _ => DEFAULT_PATHS
+        };
+
+        let paths = paths_graph.read().unwrap().k_shortest_paths(&from, &to, k);
+        if paths.is_empty() { return Ok(iron::Response::with(iron::status::NotFound)) }
+        let paths = paths.iter()
+            .map(|path| format!("{{ \"distance\": {}, \"nodes\": [{}] }}",
+                                path.weight(),
+                                path.nodes().iter().map(|x| json_string(x)).collect::<Vec<_>>().join(", ")))
+            .collect::<Vec<_>>()
+            .join(",\n    ");
+        let final_json = format!("{{ \"paths\": [\n    {}\n] }}", paths);
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "paths");
+
+    router.post("/hops", move |r: &mut iron::Request| {
+        log!("Server", "serving /hops ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        let (from, to) = match (map.find(&["from"]), map.find(&["to"])) {
+            (Some(&Value::String(ref f)), Some(&Value::String(ref t))) => (f.clone(), t.clone()),
+            _ => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+
+        let graph = hops_graph.read().unwrap();
+        let (hops, count) = match graph.count_hop_paths(&from, &to) {
+            Some(x) => x,
+            None => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+        let paths = graph.all_hop_paths(&from, &to, MAX_PATHS).iter()
+            .map(|path| format!("[{}]", path.nodes().iter().map(|x| json_string(x)).collect::<Vec<_>>().join(", ")))
+            .collect::<Vec<_>>()
+            .join(",\n    ");
+        let final_json = format!("{{ \"hops\": {}, \"count\": {}, \"paths\": [\n    {}\n] }}", hops, count, paths);
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "hops");
+
+    // Each takes n, how many pages to return, and returns them highest scored first.
+    let count = move |map: &params::Map| match map.find(&["n"]) {
+        Some(&Value::String(ref n)) => n.parse::<usize>().ok(),
+        _ => Some(top)
+    };
+    let pages_json = |pages: Vec<(Arc<String>, f64)>| {
+        let pages = pages.iter()
+            .map(|&(ref url, score)| format!("{{ \"url\": {}, \"score\": {} }}", json_string(url), score))
+            .collect::<Vec<_>>()
+            .join(",\n    ");
+        format!("[\n    {}\n]", pages)
+    };
+
+    router.post("/top", move |r: &mut iron::Request| {
+        log!("Server", "serving /top ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        let n = match count(map) {
+            Some(n) => n,
+            None => return Ok(iron::Response::with(iron::status::BadRequest))
+        };
+        let final_json = format!("{{ \"pages\": {} }}", pages_json(top_graph.read().unwrap().top(&pagerank.values, n, &[])));
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "top");
+
+    router.post("/related", move |r: &mut iron::Request| {
+        log!("Server", "serving /related ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        // url is either one page or a list of them.
+        let urls = match map.find(&["url"]) {
+            Some(&Value::String(ref u)) => vec![u.clone()],
+            Some(&Value::Array(ref us)) => us.iter().filter_map(|x| match *x {
+                Value::String(ref u) => Some(u.clone()),
+                _ => None
+            }).collect(),
+            _ => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+        let n = match count(map) {
+            Some(n) => n,
+            None => return Ok(iron::Response::with(iron::status::BadRequest))
+        };
+
+        let graph = related_graph.read().unwrap();
+        let seeds = urls.iter().filter_map(|x| graph.resolve(x)).map(|x| (*x).clone()).collect::<Vec<String>>();
+        if seeds.is_empty() { return Ok(iron::Response::with(iron::status::NotFound)) }
+        let scores = graph.personalized_pagerank(&seeds, damping, tolerance);
+        let final_json = format!("{{ \"pages\": {} }}", pages_json(graph.top(&scores, n, &seeds)));
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "related");
+
+    router.post("/hits", move |r: &mut iron::Request| {
+        log!("Server", "serving /hits ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        let n = match count(map) {
+            Some(n) => n,
+            None => return Ok(iron::Response::with(iron::status::BadRequest))
+        };
+        let graph = hits_graph.read().unwrap();
+        // Given a url, only its neighbourhood is scored.
+        let (hubs, authorities) = match map.find(&["url"]) {
+            Some(&Value::String(ref u)) => match graph.resolve(u) {
+                Some(url) => graph.focused_hits(&[(*url).clone()], tolerance),
+                None => return Ok(iron::Response::with(iron::status::NotFound))
+            },
+            _ => graph.hits(tolerance)
+        };
+        let final_json = format!("{{ \"hubs\": {},\n  \"authorities\": {} }}",
+                                 pages_json(graph.top(&hubs, n, &[])),
+                                 pages_json(graph.top(&authorities, n, &[])));
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "hits");
+
+    let cache = Mutex::new(PTree::<String, Page>::open(&config.path("cache")).ok());
+    router.post("/page", move |r: &mut iron::Request| {
+        log!("Server", "serving /page ...");
+        let map = r.get_ref::<Params>().unwrap();
+
+        let url = match map.find(&["url"]) {
+            Some(&Value::String(ref u)) => u.clone(),
+            _ => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+        let url = page_graph.read().unwrap().resolve(&url).map_or(url, |x| x.as_ref().clone());
+        let page = match *cache.lock().unwrap() {
+            Some(ref mut cache) => match cache.search(&url) {
+                Ok(Some(page)) => page,
+                _ => return Ok(iron::Response::with(iron::status::NotFound))
+            },
+            None => return Ok(iron::Response::with(iron::status::NotFound))
+        };
+
+        let strings = |v: &Vec<String>| format!("[{}]", v.iter().map(|x| json_string(x)).collect::<Vec<_>>().join(", "));
+        let infobox = page.infobox.iter()
+            .map(|&(ref label, ref value)| format!("[{}, {}]", json_string(label), json_string(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let final_json = format!("{{ \"url\": {}, \"title\": {}, \"summary\": {}, \"categories\": {}, \"headings\": {}, \"infobox\": [{}], \"disambiguation\": {} }}",
+                                 json_string(&page.url),
+                                 json_string(&page.title),
+                                 json_string(&page.summary),
+                                 strings(&page.categories),
+                                 strings(&page.headings),
+                                 infobox,
+                                 page.disambiguation);
+        let mut resp = iron::Response::with((Status::Ok, final_json));
+        resp.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Json, vec![])));
+        Ok(resp)
+    }, "page");
+
+    log!("Log", "Listening on {}", config.listen);
+    Iron::new(router).http(config.listen.as_str()).unwrap();
+}
+
+/// Builds the LSH index over the cache, signing any pages that haven't been yet.
+fn open_lsh(config: &Config) -> Result<LshIndex, io::Error> {
+    let mut cache;
+    check!(PTree::<String, Page>::open(&config.path("cache")), cache);
+    let mut signatures;
+    check!(PTree::<String, Signature>::open_or_new(&config.path("signatures")), signatures);
+    LshIndex::from_cache(&mut cache, &mut signatures)
+}
+
+/// Prints the pages most similar to url, whether or not they are linked.
+fn similar(config: &Config, url: &str) {
+    let index = match open_lsh(config) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to build the similarity index, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let url = link::normalize(url).unwrap_or(url.to_string());
+    let url = match AliasTable::open(&config.path("aliases")) {
+        Ok(mut aliases) => aliases.resolve(&url),
+        Err(_) => url
+    };
+    match index.similar(&url, config.similar_count) {
+        Some(similar) => {
+            println!("Pages most similar to {}:", url);
+            for (other, similarity) in similar.into_iter() {
+                println!("{:.3}  {}", similarity, other);
+            }
+        },
+        None => error!("\"{}\" isn't in the cache", url)
+    }
+}
+
+/// Prints every pair of pages that are near duplicates of one another.
+fn duplicates(config: &Config) {
+    let index = match open_lsh(config) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to build the similarity index, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let duplicates = index.duplicates(config.duplicate_threshold);
+    println!("{} near duplicate pairs among {} pages:", duplicates.len(), index.len());
+    for (a, b, similarity) in duplicates.into_iter() {
+        println!("{:.3}  {}  {}", similarity, a, b);
+    }
+}
+
+/// How many random queries `landmarks` times each path finding algorithm on.
+const BENCHMARK_QUERIES: usize = 200;
+
+/// Reads the landmarks saved next to the graph, if there are any that were computed on it.
+fn open_landmarks(config: &Config, graph: &AssociatedGraph<String>) -> Option<Landmarks> {
+    match Landmarks::from_disk(&config.path("landmarks")) {
+        Ok(landmarks) => {
+            if landmarks.fits(graph.graph()) {
+                Some(landmarks)
+            } else {
+                error!("The landmarks were computed on another graph, run landmarks again to use them");
+                None
+            }
+        },
+        Err(_) => None
+    }
+}
+
+/// Picks landmarks in the graph, saves their distances next to it and reports how much faster paths are
+/// found with them than with A* without a heuristic (plain Dijkstra) and with shortest_path, which /path
+/// falls back on without landmarks.
+fn build_landmarks(config: &Config, source: &PageSource) {
+    let graph = match load_graph(config, source) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to load graph, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let graph = graph.graph();
+    log!("Info", "Picking {} landmarks ({})...", config.landmarks, config.landmark_selection);
+    let start = Instant::now();
+    let landmarks = match Landmarks::select(graph, config.landmarks, &config.landmark_selection) {
+        Some(x) => x,
+        None => {
+            error!("Unknown landmark selection \"{}\"", config.landmark_selection);
+            return;
+        }
+    };
+    log!("Info", "Computed distances for {} landmarks in {:?}", landmarks.len(), start.elapsed());
+    if let Err(e) = landmarks.persist(&config.path("landmarks")) {
+        error!("Failed to save landmarks, encountered error \"{}\"", e);
+        return;
+    }
+    if graph.count() == 0 { return }
+
+    // xorshift, seeded from the clock; the queries only need to be spread over the graph.
+    let mut x = SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|x| x.subsec_nanos() as u64).unwrap_or(0) | 1;
+    let mut random = || { x ^= x << 13; x ^= x >> 7; x ^= x << 17; (x % graph.count() as u64) as usize };
+    let queries = (0..BENCHMARK_QUERIES).map(|_| (random(), random())).collect::<Vec<_>>();
+
+    let time = |find: &Fn(usize, usize) -> Option<(Vec<usize>, f64)>| {
+        let start = Instant::now();
+        let distances = queries.iter().map(|&(from, to)| find(from, to).map(|x| x.1)).collect::<Vec<_>>();
+        let elapsed = start.elapsed();
+        (elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1e6, distances)
+    };
+    let (zero, expected) = time(&|from, to| graph.astar(from, to, &Zero));
+    let (shortest_path, shortest_path_distances) = time(&|from, to| graph.shortest_path(from, to));
+    let (alt, alt_distances) = time(&|from, to| graph.astar(from, to, &landmarks));
+    for distances in [shortest_path_distances, alt_distances].iter() {
+        let wrong = expected.iter().zip(distances.iter())
+            .filter(|&(a, b)| a.is_some() != b.is_some() || (a.unwrap_or(0.0) - b.unwrap_or(0.0)).abs() > 1e-9)
+            .count();
+        if wrong > 0 {
+            error!("{} of {} paths disagree with A* without a heuristic", wrong, queries.len());
+        }
+    }
+    let per_query = |ms: f64| ms / queries.len() as f64;
+    println!("{} random queries, {} with a path:", queries.len(), expected.iter().filter(|x| x.is_some()).count());
+    println!("    a*, zero heuristic                     {:.3} ms per query", per_query(zero));
+    println!("    shortest_path (bidirectional dijkstra) {:.3} ms per query ({:.1}x)", per_query(shortest_path), zero / shortest_path);
+    println!("    a*, alt with {:>3} landmarks             {:.3} ms per query ({:.1}x)", landmarks.len(), per_query(alt), zero / alt);
+}
+
+/// Computes the PageRank of every page in the graph and saves it next to the graph.
+fn compute_pagerank(config: &Config, graph: &AssociatedGraph<String>) -> Scores {
+    let start = Instant::now();
+    let scores = Scores::new(graph.graph(), graph.pagerank(config.damping, config.tolerance));
+    log!("Info", "Computed PageRank of {} pages in {:?}", scores.values.len(), start.elapsed());
+    if let Err(e) = scores.persist(&config.path("pagerank")) {
+        error!("Failed to save PageRank, encountered error \"{}\"", e);
+    }
+    scores
+}
+
+/// Reads the PageRank saved next to the graph, computing it if it hasn't been or the graph has changed since.
+fn open_pagerank(config: &Config, graph: &AssociatedGraph<String>) -> Scores {
+    match Scores::from_disk(&config.path("pagerank")) {
+        Ok(ref scores) if !scores.fits(graph.graph()) => log!("Info", "The graph has changed, recomputing PageRank"),
+        Ok(scores) => return scores,
+        Err(_) => {}
+    }
+    compute_pagerank(config, graph)
 }
 
-fn clean() {
-    if let Err(e) = fs::remove_dir_all("data") {
+/// Computes the PageRank of every page and prints the highest ranked.
+fn rank(config: &Config, source: &PageSource) {
+    let graph = match load_graph(config, source) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to load graph, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let scores = compute_pagerank(config, &graph);
+    println!("Highest PageRank of {} pages:", graph.len());
+    for (url, score) in graph.top(&scores.values, config.top, &[]).into_iter() {
+        println!("{:.6}  {}", score, url);
+    }
+}
+
+/// Prints the pages most related to urls by PageRank personalized to them.
+fn related(config: &Config, source: &PageSource, urls: &[String]) {
+    let graph = match load_graph(config, source) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to load graph, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let mut seeds = vec![];
+    for url in urls.iter() {
+        let url = link::normalize(url).unwrap_or(url.clone());
+        match graph.resolve(&url) {
+            Some(url) => seeds.push((*url).clone()),
+            None => error!("\"{}\" isn't in the graph", url)
+        }
+    }
+    if seeds.is_empty() { return }
+    let scores = graph.personalized_pagerank(&seeds, config.damping, config.tolerance);
+    println!("Pages most related to {}:", seeds.join(", "));
+    for (url, score) in graph.top(&scores, config.top, &seeds).into_iter() {
+        println!("{:.6}  {}", score, url);
+    }
+}
+
+/// Computes the HITS scores of every page and prints the best hubs and authorities.
+fn hits(config: &Config, source: &PageSource) {
+    let graph = match load_graph(config, source) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to load graph, encountered error \"{}\"", e);
+            return;
+        }
+    };
+    let start = Instant::now();
+    let (hubs, authorities) = graph.hits(config.tolerance);
+    log!("Info", "Computed HITS of {} pages in {:?}", graph.len(), start.elapsed());
+    println!("Best hubs:");
+    for (url, score) in graph.top(&hubs, config.top, &[]).into_iter() {
+        println!("{:.6}  {}", score, url);
+    }
+    println!("Best authorities:");
+    for (url, score) in graph.top(&authorities, config.top, &[]).into_iter() {
+        println!("{:.6}  {}", score, url);
+    }
+}
+
+fn clean(config: &Config) {
+    if let Err(e) = fs::remove_dir_all(&config.data_dir) {
         error!("Failed to remove data directory, encountered error \"{}\"", e);
     }
-    if let Err(e) = fs::create_dir("data") {
+    if let Err(e) = fs::create_dir(&config.data_dir) {
         error!("Failed to create data directory, encountered error \"{}\"", e);
     }
 }
 
-fn test() {
-    let mut graph = load_graph().unwrap();
-    {
-        let st = graph.shortest_path_tree(&"/wiki/Objective-C".to_string()).unwrap();
-        if !st.spans() {
-            error!("Does not scpan :(");
+/// Prints how many components of each size there are, largest first, at most limit sizes.
+fn print_sizes<T>(components: &[Vec<T>], limit: usize) {
+    let mut sizes = HashMap::new();
+    for component in components.iter() {
+        *sizes.entry(component.len()).or_insert(0) += 1;
+    }
+    let mut sizes = sizes.into_iter().collect::<Vec<(usize, usize)>>();
+    sizes.sort_by_key(|x| ::std::cmp::Reverse(x.0));
+    for &(size, count) in sizes.iter().take(limit) {
+        println!("    {:>8} of {} pages", count, size);
+    }
+    if sizes.len() > limit {
+        println!("    ... and {} smaller sizes", sizes.len() - limit);
+    }
+}
+
+/// Reports how the graph falls into components and which pages can't be reached from the seeds.
+fn components(config: &Config, source: &PageSource) {
+    let graph = match load_graph(config, source) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to load graph, encountered error \"{}\"", e);
             return;
         }
+    };
+    println!("{} pages, {} links", graph.len(), graph.graph().edge_count());
+
+    let strong = graph.strongly_connected_components();
+    println!("{} strongly connected components, the largest of {} pages:",
+             strong.len(), strong.iter().map(|x| x.len()).max().unwrap_or(0));
+    print_sizes(&strong, config.top);
+    let weak = graph.weakly_connected_components();
+    println!("{} weakly connected components, the largest of {} pages:",
+             weak.len(), weak.iter().map(|x| x.len()).max().unwrap_or(0));
+    print_sizes(&weak, config.top);
+    let condensation = graph.condensation();
+    println!("Condensed to a DAG of {} components and {} links, {} with no links in and {} with no links out",
+             condensation.len(), condensation.edge_count(), condensation.sources().len(), condensation.sinks().len());
+
+    let mut seeds = vec![];
+    for seed in config.seeds().into_iter() {
+        if graph.contains_key(&seed) {
+            seeds.push(seed);
+        } else {
+            error!("The seed \"{}\" isn't in the graph", seed);
+        }
+    }
+    if seeds.is_empty() { return }
+    let unreachable = graph.unreachable(&seeds);
+    println!("{} pages can't be reached from {}:", unreachable.len(), seeds.join(", "));
+    for url in unreachable.iter() {
+        println!("    {}", url);
     }
 }
 
 fn main() {
-    //test();
-    let mut args = env::args();
-    let mut should_clean: bool = false;
-    let mut should_serve: bool = false;
-    let mut should_create: bool = false;
-    let mut should_load: bool = false;
-    let mut n = 10i32;
-    for arg in args {
-        if arg.as_str() == "clean" {
-            should_clean = true;
-        } else if arg.as_str() == "serve" || arg.as_str() == "server" {
-            should_serve = true;
-        } else if arg.as_str() == "create" {
-            should_create = true;
-        } else if arg.as_str() == "load" {
-            should_load = true;
-        } else if let Ok(x) = arg.parse::<i32>() {
-            n = x;
-        }
-    }
-    if should_clean {
-        clean();
-    }
-    if should_create {
-        create_graph(n);
-    }
-    if should_load {
-        load_pages(n);
-    }
-    if should_serve {
-        server();
+    let (commands, config) = match Config::from_args(env::args().skip(1).collect()) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("{}", e);
+            print!("{}", config::USAGE);
+            return;
+        }
+    };
+    if commands.is_empty() || commands.contains(&Command::Help) {
+        print!("{}", config::USAGE);
+        return;
+    }
+    let source = open_source(&config);
+    for command in commands.iter() {
+        match *command {
+            Command::Help => {},
+            Command::Clean => clean(&config),
+            Command::Import(ref xml) => import_dump(&config, xml),
+            Command::Create => create_graph(&config, &*source),
+            Command::Load => load_pages(&config, source.clone()),
+            Command::Similar(ref url) => similar(&config, url),
+            Command::Duplicates => duplicates(&config),
+            Command::Landmarks => build_landmarks(&config, &*source),
+            Command::Rank => rank(&config, &*source),
+            Command::Related(ref urls) => related(&config, &*source, urls),
+            Command::Hits => hits(&config, &*source),
+            Command::Components => components(&config, &*source),
+            Command::Serve => server(&config, &*source)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use page_source::MemorySource;
+
+    fn html(links: &[&str]) -> String {
+        let links = links.iter().map(|x| format!("<a href=\"/wiki/{}\">{}</a>", x, x)).collect::<Vec<_>>().join(" ");
+        format!("<html><body><div class=\"mw-parser-output\"><p>Some text about stars {}</p></div></body></html>", links)
+    }
+
+    /// A small wiki: Alpha links to Beta, Gamma and a page that doesn't exist, Beta back to Alpha, and Gamma
+    /// to Delta, which links nowhere.
+    fn wiki() -> MemorySource {
+        let mut source = MemorySource::new();
+        source.insert("/wiki/Alpha", &html(&["Beta", "Gamma", "Missing"]));
+        source.insert("/wiki/Beta", &html(&["Alpha"]));
+        source.insert("/wiki/Gamma", &html(&["Delta"]));
+        source.insert("/wiki/Delta", &html(&[]));
+        source
+    }
+
+    fn config(name: &str) -> Config {
+        let dir = env::temp_dir().join(format!("crate-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut config = Config::new();
+        config.data_dir = dir.to_string_lossy().into_owned();
+        config.seeds = vec!["/wiki/Alpha".to_string()];
+        config.max_pages = 100;
+        config.threads = 4;
+        config
+    }
+
+    fn cached(config: &Config) -> Vec<String> {
+        let mut cache = PTree::<String, Page>::open(&config.path("cache")).unwrap();
+        let mut urls = ["/wiki/Alpha", "/wiki/Beta", "/wiki/Gamma", "/wiki/Delta", "/wiki/Missing"].iter()
+            .filter(|x| cache.contains_key(&x.to_string()).unwrap())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        urls.sort();
+        urls
+    }
+
+    #[test]
+    fn crawl_stops_when_the_wiki_runs_out() {
+        let config = config("crawl");
+        load_pages(&config, Arc::new(wiki()));
+        assert_eq!(cached(&config), vec!["/wiki/Alpha", "/wiki/Beta", "/wiki/Delta", "/wiki/Gamma"]);
+        let _ = fs::remove_dir_all(&config.data_dir);
+    }
+
+    #[test]
+    fn term_ids_of_a_cache_are_not_reassigned() {
+        let config = config("terms");
+        assert!(open_tokenizer(&config).is_ok());
+        load_pages(&config, Arc::new(wiki()));
+        assert!(open_tokenizer(&config).is_ok());
+        fs::remove_file(config.path("terms")).unwrap();
+        assert!(open_tokenizer(&config).is_err());
+        let _ = fs::remove_dir_all(&config.data_dir);
+    }
+
+    #[test]
+    fn crawl_stops_at_max_depth() {
+        let mut config = config("depth");
+        config.max_depth = Some(1);
+        load_pages(&config, Arc::new(wiki()));
+        assert_eq!(cached(&config), vec!["/wiki/Alpha", "/wiki/Beta", "/wiki/Gamma"]);
+        let _ = fs::remove_dir_all(&config.data_dir);
     }
 }
@@ -0,0 +1,254 @@
+use frequency_table::FrequencyTable;
+use page::Page;
+use ptree::PTree;
+use random_access_file::Serialize;
+use std::collections::{ HashMap, HashSet };
+use std::io;
+use std::io::{ Read, Write };
+
+/// The number of hash functions in a signature.
+pub const NUM_HASHES: usize = 128;
+
+/// Signatures are split into this many bands of NUM_HASHES / BANDS rows for LSH. Pages whose signatures agree on
+/// every row of at least one band are candidates; with 32 bands of 4 that catches most pairs with a Jaccard
+/// similarity above about 0.4.
+pub const BANDS: usize = 32;
+
+const ROWS: usize = NUM_HASHES / BANDS;
+
+/// The splitmix64 finalizer, a cheap and good mixing function. Unlike the standard library's hasher it is
+/// guaranteed to hash the same way forever, so signatures on disk stay valid.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// A hash of the term ids and counts in words, to notice when a signature was made from a different version
+/// of a page, or before its terms were given different ids.
+fn fingerprint(words: &FrequencyTable) -> u64 {
+    words.terms().iter().fold(words.len() as u64, |acc, &(term, freq)| mix(acc ^ mix(((term as u64) << 32) | freq.to_bits() as u64)))
+}
+
+/// A MinHash signature of the set of words on a page. The share of positions at which two signatures agree
+/// estimates the Jaccard similarity of the pages' word sets.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Signature {
+    pub hashes: Vec<u64>,
+    /// The fingerprint of the word counts the signature was made from.
+    source: u64
+}
+
+impl Signature {
+    pub fn new(words: &FrequencyTable) -> Signature {
+        let mut mins = vec![u64::max_value(); NUM_HASHES];
+        for &(term, _) in words.terms().iter() {
+            let hash = mix(term as u64);
+            for (i, min) in mins.iter_mut().enumerate() {
+                let h = mix(hash ^ mix(i as u64));
+                if h < *min { *min = h; }
+            }
+        }
+        Signature { hashes: mins, source: fingerprint(words) }
+    }
+
+    /// Returns true if the signature was made from exactly these word counts.
+    pub fn signs(&self, words: &FrequencyTable) -> bool {
+        self.source == fingerprint(words)
+    }
+
+    /// The estimated Jaccard similarity of the word sets of the pages the signatures came from.
+    pub fn similarity(&self, other: &Signature) -> f64 {
+        let same = self.hashes.iter().zip(other.hashes.iter()).filter(|&(a, b)| a == b).count();
+        same as f64 / self.hashes.len().max(1) as f64
+    }
+
+    fn band(&self, band: usize) -> u64 {
+        self.hashes[band * ROWS..(band + 1) * ROWS].iter().fold(band as u64, |acc, x| mix(acc ^ *x))
+    }
+}
+
+impl Serialize for Signature {
+    type DeserializeOutput = Signature;
+    fn deserialize(from: &mut Read) -> Result<Signature, io::Error> {
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut v = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let x;
+            check!(u64::deserialize(from), x);
+            v.push(x);
+        }
+        let source;
+        check!(u64::deserialize(from), source);
+        Ok(Signature { hashes: v, source: source })
+    }
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.hashes.len().serialize(to));
+        for x in self.hashes.iter() {
+            check!(x.serialize(to));
+        }
+        check!(self.source.serialize(to));
+        Ok(())
+    }
+    fn serialized_len(&self) -> u64 {
+        16 + 8 * self.hashes.len() as u64
+    }
+}
+
+/// Locality sensitive hashing over MinHash signatures, for finding similar pages without comparing every pair.
+pub struct LshIndex {
+    signatures: HashMap<String, Signature>,
+    buckets: HashMap<u64, Vec<String>>
+}
+
+impl LshIndex {
+    pub fn new() -> LshIndex {
+        LshIndex { signatures: HashMap::new(), buckets: HashMap::new() }
+    }
+
+    /// Builds the index out of every page in the cache, signing pages that don't have a signature in
+    /// signatures (the tree kept next to the cache) yet, or whose words have changed since they were signed.
+    pub fn from_cache(cache: &mut PTree<String, Page>, signatures: &mut PTree<String, Signature>) -> Result<LshIndex, io::Error> {
+        let mut index = LshIndex::new();
+        let keys;
+        check!(cache.keys(), keys);
+        let mut signed = 0;
+        for url in keys.into_iter() {
+            if index.signatures.contains_key(&url) { continue }
+            let page = match cache.search(&url) {
+                // Pages without words would all look identical.
+                Ok(Some(ref page)) if page.word_freq.is_empty() => continue,
+                Ok(Some(page)) => page,
+                _ => continue
+            };
+            // Signatures in an older format don't deserialize, and get replaced.
+            let signature = match signatures.search(&url) {
+                Ok(Some(signature)) if signature.signs(&page.word_freq) => signature,
+                _ => {
+                    let signature = Signature::new(&page.word_freq);
+                    check!(signatures.insert_or_replace(&url, &signature));
+                    signed += 1;
+                    signature
+                }
+            };
+            index.insert(url, signature);
+        }
+        if signed > 0 {
+            log!("Info", "Signed {} new or changed pages", signed);
+            check!(signatures.flush());
+        }
+        Ok(index)
+    }
+
+    pub fn len(&self) -> usize { self.signatures.len() }
+
+    pub fn insert(&mut self, url: String, signature: Signature) {
+        for band in 0..BANDS {
+            self.buckets.entry(signature.band(band)).or_insert(vec![]).push(url.clone());
+        }
+        self.signatures.insert(url, signature);
+    }
+
+    /// Pages that share a band with signature.
+    fn candidates(&self, signature: &Signature) -> HashSet<&String> {
+        let mut candidates = HashSet::new();
+        for band in 0..BANDS {
+            if let Some(urls) = self.buckets.get(&signature.band(band)) {
+                candidates.extend(urls.iter());
+            }
+        }
+        candidates
+    }
+
+    /// Up to n of the pages most similar to url, most similar first, with their estimated similarity.
+    /// Returns None if url isn't in the index.
+    pub fn similar(&self, url: &str, n: usize) -> Option<Vec<(String, f64)>> {
+        let signature = match self.signatures.get(url) {
+            Some(x) => x,
+            None => return None
+        };
+        let mut similar = self.candidates(signature).into_iter()
+            .filter(|x| x.as_str() != url)
+            .map(|x| (x.clone(), signature.similarity(&self.signatures[x])))
+            .collect::<Vec<_>>();
+        similar.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+        similar.truncate(n);
+        Some(similar)
+    }
+
+    /// Every pair of pages whose estimated similarity is at least threshold, most similar first.
+    pub fn duplicates(&self, threshold: f64) -> Vec<(String, String, f64)> {
+        let mut pairs = HashSet::new();
+        for urls in self.buckets.values() {
+            for (i, a) in urls.iter().enumerate() {
+                for b in urls[i + 1..].iter() {
+                    if a == b { continue }
+                    pairs.insert(if a < b { (a, b) } else { (b, a) });
+                }
+            }
+        }
+        let mut duplicates = pairs.into_iter()
+            .map(|(a, b)| (a.clone(), b.clone(), self.signatures[a].similarity(&self.signatures[b])))
+            .filter(|x| x.2 >= threshold)
+            .collect::<Vec<_>>();
+        duplicates.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap().then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+        duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Word counts of the words prefix0, prefix1, ... for every number in range.
+    fn words(prefix: u32, range: ::std::ops::Range<u32>) -> FrequencyTable {
+        FrequencyTable::new_from(range.map(|x| prefix * 1000 + x).collect())
+    }
+
+    #[test]
+    fn similarity_estimates_jaccard() {
+        let a = Signature::new(&words(1, 0..200));
+        // 100 shared words out of 300.
+        let b = Signature::new(&words(1, 100..300));
+        assert_eq!(a.similarity(&a), 1.0);
+        assert!((a.similarity(&b) - 1.0 / 3.0).abs() < 0.15);
+        assert!(a.similarity(&Signature::new(&words(2, 0..200))) < 0.1);
+    }
+
+    #[test]
+    fn lsh_finds_near_duplicates() {
+        let mut index = LshIndex::new();
+        index.insert("a".to_string(), Signature::new(&words(1, 0..200)));
+        index.insert("b".to_string(), Signature::new(&words(1, 0..202)));
+        index.insert("c".to_string(), Signature::new(&words(2, 0..200)));
+        index.insert("d".to_string(), Signature::new(&words(1, 100..300)));
+        assert_eq!(index.len(), 4);
+
+        let similar = index.similar("a", 5).unwrap();
+        assert_eq!(similar[0].0, "b");
+        assert!(similar[0].1 > 0.9);
+        assert!(similar.iter().all(|x| x.0 != "c"));
+        assert!(index.similar("e", 5).is_none());
+
+        let duplicates = index.duplicates(0.9);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!((duplicates[0].0.as_str(), duplicates[0].1.as_str()), ("a", "b"));
+    }
+
+    #[test]
+    fn signature_remembers_what_it_signed() {
+        let signature = Signature::new(&words(1, 0..50));
+        let mut buf = vec![];
+        signature.serialize(&mut buf).unwrap();
+        assert_eq!(buf.len() as u64, signature.serialized_len());
+        let read = Signature::deserialize(&mut &buf[..]).unwrap();
+        assert_eq!(read, signature);
+
+        assert!(read.signs(&words(1, 0..50)));
+        assert!(!read.signs(&words(1, 0..51)));
+        // The same words, one of them counted twice.
+        assert!(!read.signs(&FrequencyTable::new_from((1000..1050).chain(1000..1001).collect())));
+    }
+}
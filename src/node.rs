@@ -1,24 +1,15 @@
-use std::usize;
-use random_access_file;
 use random_access_file::Serialize;
 use std::io;
 use std::io::{ Read, Write };
 use edge::Edge;
 use std::u64;
-use check;
 
 pub struct Node {
     /// The id for the Node. This can be used simply to distinguish nodes, or as an index to some data cache, or anything else.
     pub id: usize,
 
     /// A list of all the connections this node makes.
-    pub edges: Vec<Edge>,
-
-    /// Used to mark if this node has been visited in some of the algorithms.
-    pub marker: usize,
-
-    /// Used to mark the next node in a path from Node to Node. If the next node in the path doesn't exist it will be None.
-    pub next: usize
+    pub edges: Vec<Edge>
 }
 
 /// Public methods for Node
@@ -26,8 +17,6 @@ impl Node {
     pub fn new(id: usize) -> Self {
         Node {
             edges: vec![],
-            marker: 0,
-            next: usize::MAX,
             id: id
         }
     }
@@ -49,7 +38,7 @@ impl<'a> Serialize for EdgeList<'a> {
         let len;
         check!(u64::deserialize(read), len);
         let mut r = vec![];
-        for i in 0..len {
+        for _ in 0..len {
             let edge;
             check!(Edge::deserialize(read), edge);
             r.push(edge);
@@ -80,8 +69,6 @@ impl Serialize for Node {
 
         Ok(Node {
             edges: edges,
-            marker: 0,
-            next: usize::MAX,
             id: id as usize
         })
     }
@@ -1,88 +1,285 @@
-use hyper::client;
-use hyper::client::*;
-use hyper::net::HttpsConnector;
-use hyper_native_tls::NativeTlsClient;
 use select::document::Document;
 use select::predicate::{Predicate, Attr, Class, Name};
-use regex::Regex;
-use lazy_static;
+use content::ContentFilter;
+use link;
+use link::{ Link, LinkRegion };
+use select::node::Node;
+use tokenizer;
+use tokenizer::Tokenizer;
 use frequency_table::FrequencyTable;
-use persistable_hash::PersistableHashMap;
 use std::io;
 use random_access_file::Serialize;
 use std::io::{ Write, Read };
 
+/// Written at the start of every serialized Page, so pages cached in an older format are read as an error
+/// (and refetched) rather than as garbage. Pages from before versioning start with the length of their url,
+/// which is never less than 7.
+const PAGE_FORMAT_VERSION: u64 = 4;
+
 pub struct Page {
+    /// The canonical url of the page. This differs from the requested url if the request was redirected.
     pub url: String,
+    /// The title as displayed on the page, e.g. "Solar System"
+    pub title: String,
+    /// The lead paragraph.
+    pub summary: String,
+    pub categories: Vec<String>,
+    /// Section headings, in the order they appear.
+    pub headings: Vec<String>,
+    /// The label / value rows of the infobox, if the page has one.
+    pub infobox: Vec<(String, String)>,
+    pub disambiguation: bool,
     pub word_freq: FrequencyTable,
-    pub links: Vec<String>
+    /// One entry per article linked to, in the order they first appear.
+    pub links: Vec<Link>
+}
+
+/// Collapses runs of whitespace and removes citation markers.
+fn clean_text(text: &str) -> String {
+    let text = tokenizer::strip_markers(text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The title a url would have if the page doesn't say, e.g. "/wiki/Solar_System" gives "Solar System"
+fn title_from_url(url: &str) -> String {
+    let title = url.strip_prefix(link::WIKI_PREFIX).unwrap_or(url);
+    title.replace('_', " ")
 }
 
-lazy_static! {
-    static ref LINK_REGEX: Regex = Regex::new(r#"^/wiki/[a-zA-Z0-9_\-+\(\)]+$"#).unwrap();
+/// The text of a section heading of the article, or None if node isn't one.
+fn heading_text(node: &Node) -> Option<String> {
+    if !node.is(Name("h2").or(Name("h3")).or(Name("h4"))) { return None }
+    // Older markup wraps the heading text in a span.mw-headline, newer markup wraps the heading in a div.mw-heading.
+    // Headings with neither (e.g. "Navigation menu") aren't part of the article.
+    match node.find(Class("mw-headline")).next() {
+        Some(headline) => Some(clean_text(&headline.text())),
+        None => match node.parent() {
+            Some(ref parent) if parent.is(Class("mw-heading")) => Some(clean_text(&node.text())),
+            _ => None
+        }
+    }
+}
+
+/// Works out which region of the page the link node is in. first_heading is the index of the first section heading.
+fn link_region(node: &Node, first_heading: usize) -> LinkRegion {
+    let mut ancestor = node.parent();
+    while let Some(x) = ancestor {
+        if x.is(Class("navbox").or(Class("vertical-navbox")).or(Class("sidebar")).or(Attr("role", "navigation"))) {
+            return LinkRegion::Navbox;
+        }
+        if x.is(Class("infobox")) {
+            return LinkRegion::Infobox;
+        }
+        ancestor = x.parent();
+    }
+    if node.index() < first_heading { LinkRegion::Lead } else { LinkRegion::Body }
 }
 
 impl Page {
-    pub fn new(url: &str, html: &str) -> Page {
-        let re = Regex::new(r#"^/wiki/[a-zA-Z0-9_\-+\(\)]+$"#).unwrap();
+    /// Parses the html of the page that was requested at url, taking text and links only from what filter considers
+    /// content. If the html names a different canonical url (i.e. url is a redirect) the page takes on the canonical url.
+    pub fn new(url: &str, html: &str, filter: &ContentFilter, tokenizer: &Tokenizer) -> Page {
         let document = Document::from(html);
+        let mut url = url.to_string();
+        for node in document.find(Name("link").and(Attr("rel", "canonical"))) {
+            if let Some(href) = node.attr("href") {
+                if let Some(canonical) = href.find(link::WIKI_PREFIX).and_then(|ind| link::normalize(&href[ind..])) {
+                    url = canonical;
+                }
+            }
+        }
+        let root = filter.root(&document);
+        let headings = document.find(Name("h2").or(Name("h3")).or(Name("h4")))
+            .filter(|node| filter.keeps(node, root))
+            .filter_map(|node| {
+                let level = node.name().and_then(|x| x[1..].parse::<u8>().ok()).unwrap_or(2);
+                heading_text(&node).map(|text| (node.index(), level, text))
+            })
+            .collect::<Vec<_>>();
+        let first_heading = headings.first().map_or(usize::max_value(), |&(ind, _, _)| ind);
+        // The index of each heading and whether its section is excluded. Subsections of an excluded section are too.
+        let mut sections = vec![];
+        let mut excluded_level = None;
+        for &(ind, level, ref text) in headings.iter() {
+            if excluded_level.is_some_and(|x| level <= x) { excluded_level = None; }
+            if excluded_level.is_none() && filter.excludes_section(text) { excluded_level = Some(level); }
+            sections.push((ind, excluded_level.is_some()));
+        }
+        let is_content = |node: &Node| {
+            let excluded_section = sections.iter().take_while(|&&(ind, _)| ind < node.index()).last().is_some_and(|&(_, x)| x);
+            !excluded_section && filter.keeps(node, root)
+        };
+
         let mut corpus = String::new();
-        let mut links = vec![];
-        let space = " ".to_string();
-        for node in document.find(Name("p")) {
-            corpus += node.text().as_str();
+        let mut summary = None;
+        for node in document.find(Name("p")).filter(&is_content) {
+            let text = filter.text(&node);
+            if summary.is_none() && !clean_text(&text).is_empty() {
+                summary = Some(clean_text(&text));
+            }
+            corpus += text.as_str();
             corpus.push(' ');
         }
-        for node in document.find(Name("a")) {
-            if let Some(link) = node.attr("href") {
-                for cap in re.captures_iter(link) {
-                    links.push(cap[0].to_string());
+        let mut links = vec![];
+        for node in document.find(Name("a")).filter(&is_content) {
+            if let Some(url) = node.attr("href").and_then(link::normalize) {
+                links.push(Link::new(url, &filter.text(&node), link_region(&node, first_heading)));
+            }
+        }
+        let mut page = Page::from_parts(url.as_ref(), corpus.as_ref(), links, tokenizer);
+
+        if let Some(node) = document.find(Attr("id", "firstHeading")).next() {
+            page.title = clean_text(&node.text());
+        } else if let Some(node) = document.find(Name("title")).next() {
+            page.title = node.text().trim().trim_right_matches(" - Wikipedia").to_string();
+        }
+        if let Some(summary) = summary {
+            page.summary = summary;
+        }
+        for node in document.find(Attr("id", "mw-normal-catlinks").descendant(Name("li"))) {
+            page.categories.push(clean_text(&node.text()));
+        }
+        page.headings = headings.into_iter().map(|(_, _, text)| text).collect();
+        if let Some(infobox) = document.find(Name("table").and(Class("infobox"))).next() {
+            for row in infobox.find(Name("tr")) {
+                let label = row.find(Name("th")).next().map(|x| clean_text(&x.text()));
+                let value = row.find(Name("td")).next().map(|x| clean_text(&x.text()));
+                if let (Some(label), Some(value)) = (label, value) {
+                    if !label.is_empty() && !value.is_empty() {
+                        page.infobox.push((label, value));
+                    }
                 }
             }
         }
-        let word_freq = FrequencyTable::new_from_str(corpus.as_ref());
+        page.disambiguation = document.find(Attr("id", "disambigbox").or(Class("dmbox-disambig"))).next().is_some() ||
+            page.categories.iter().any(|x| x.ends_with("disambiguation pages")) ||
+            page.url.ends_with("_(disambiguation)");
+        page
+    }
+
+    /// Creates a page out of text and links that have already been extracted, e.g. from a dump. Repeated links are merged.
+    /// The title is taken from the url and the rest of the metadata is left empty.
+    pub fn from_parts(url: &str, corpus: &str, links: Vec<Link>, tokenizer: &Tokenizer) -> Page {
+        let word_freq = FrequencyTable::new_from_str(corpus, tokenizer);
         Page {
             url: url.to_string(),
-            links: links,
+            title: title_from_url(url),
+            summary: String::new(),
+            categories: vec![],
+            headings: vec![],
+            infobox: vec![],
+            disambiguation: false,
+            links: link::dedup(links),
             word_freq: word_freq
         }
     }
 }
 
+fn serialize_strings(strings: &[String], to: &mut Write) -> Result<(), io::Error> {
+    check!(strings.len().serialize(to));
+    for s in strings.iter() {
+        check!(s.serialize(to));
+    }
+    Ok(())
+}
+
+fn deserialize_strings(from: &mut Read) -> Result<Vec<String>, io::Error> {
+    let len;
+    check!(u64::deserialize(from), len);
+    let mut v = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let s;
+        check!(String::deserialize(from), s);
+        v.push(s);
+    }
+    Ok(v)
+}
+
+fn strings_len(strings: &[String]) -> u64 {
+    strings.iter().fold(8, |len, s| len + s.serialized_len())
+}
+
 impl Serialize for Page {
     type DeserializeOutput = Page;
     fn deserialize(from: &mut Read) -> Result<Self, io::Error> {
+        let version;
+        check!(u64::deserialize(from), version);
+        if version != PAGE_FORMAT_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown page format version {}", version)));
+        }
         let url;
         check!(String::deserialize(from), url);
+        let title;
+        check!(String::deserialize(from), title);
+        let summary;
+        check!(String::deserialize(from), summary);
+        let categories;
+        check!(deserialize_strings(from), categories);
+        let headings;
+        check!(deserialize_strings(from), headings);
+        let infobox_len;
+        check!(u64::deserialize(from), infobox_len);
+        let mut infobox = Vec::with_capacity(infobox_len as usize);
+        for _ in 0..infobox_len {
+            let label;
+            check!(String::deserialize(from), label);
+            let value;
+            check!(String::deserialize(from), value);
+            infobox.push((label, value));
+        }
+        let disambiguation;
+        check!(u8::deserialize(from), disambiguation);
         let word_freq;
-        check!(PersistableHashMap::<String, usize>::deserialize(from), word_freq);
+        check!(FrequencyTable::deserialize(from), word_freq);
         let len;
         check!(u64::deserialize(from), len);
-        let mut v = Vec::with_capacity(len as usize);
-        for i in 0..len {
-            let s;
-            check!(String::deserialize(from), s);
-            v.push(s);
+        let mut links = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let link;
+            check!(Link::deserialize(from), link);
+            links.push(link);
         }
         Ok(Page {
             url: url,
-            word_freq: FrequencyTable(word_freq),
-            links: v
+            title: title,
+            summary: summary,
+            categories: categories,
+            headings: headings,
+            infobox: infobox,
+            disambiguation: disambiguation != 0,
+            word_freq: word_freq,
+            links: links
         })
     }
     fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(PAGE_FORMAT_VERSION.serialize(to));
         check!(self.url.serialize(to));
-        check!(PersistableHashMap::new(&self.word_freq.0).serialize(to));
+        check!(self.title.serialize(to));
+        check!(self.summary.serialize(to));
+        check!(serialize_strings(&self.categories, to));
+        check!(serialize_strings(&self.headings, to));
+        check!(self.infobox.len().serialize(to));
+        for &(ref label, ref value) in self.infobox.iter() {
+            check!(label.serialize(to));
+            check!(value.serialize(to));
+        }
+        check!((self.disambiguation as u8).serialize(to));
+        check!(self.word_freq.serialize(to));
         check!(self.links.len().serialize(to));
-        for i in self.links.iter() {
-            check!(i.serialize(to));
+        for link in self.links.iter() {
+            check!(link.serialize(to));
         }
         Ok(())
     }
     fn serialized_len(&self) -> u64 {
-        let mut len = 8 + 8 + PersistableHashMap::new(&self.word_freq.0).serialized_len() + 8;
-        for x in self.links.iter() {
-            len += x.serialized_len();
+        let mut len = 8 + self.url.serialized_len() + self.title.serialized_len() + self.summary.serialized_len();
+        len += strings_len(&self.categories) + strings_len(&self.headings) + 8;
+        for &(ref label, ref value) in self.infobox.iter() {
+            len += label.serialized_len() + value.serialized_len();
+        }
+        len += 1 + self.word_freq.serialized_len() + 8;
+        for link in self.links.iter() {
+            len += link.serialized_len();
         }
         len
     }
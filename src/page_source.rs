@@ -0,0 +1,181 @@
+use hyper::client::Client;
+use hyper::header::UserAgent;
+use hyper::net::HttpsConnector;
+use hyper_native_tls::NativeTlsClient;
+use link;
+use politeness::{ Politeness, RateLimiter, Robots };
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::str;
+use std::thread;
+use std::time::Duration;
+
+/// Somewhere pages can be fetched from. Urls are relative to the wiki root, e.g. "/wiki/Astronomy".
+pub trait PageSource: Send + Sync {
+    /// Fetches the html for the page at url.
+    fn fetch(&self, url: &str) -> Result<String, io::Error>;
+}
+
+/// Fetches pages over http(s) from a live wiki, such as https://en.wikipedia.org
+/// Requests are rate limited, retried with exponential backoff, and checked against the wiki's robots.txt
+pub struct HttpSource {
+    client: Client,
+    domain: String,
+    host: String,
+    politeness: Politeness,
+    limiter: RateLimiter,
+    robots: Robots
+}
+
+/// The outcome of a single request.
+enum Attempt {
+    Done(String),
+    /// Worth trying again, possibly after the server told us how long to wait.
+    Retry(io::Error, Option<Duration>),
+    Fail(io::Error)
+}
+
+impl HttpSource {
+    pub fn new(domain: &str, politeness: &Politeness) -> Result<HttpSource, io::Error> {
+        let ssl = match NativeTlsClient::new() {
+            Ok(x) => x,
+            Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("{}", e)))
+        };
+        let connector = HttpsConnector::new(ssl);
+        let mut client = Client::with_connector(connector);
+        client.set_read_timeout(Some(politeness.timeout));
+        client.set_write_timeout(Some(politeness.timeout));
+        let host = match domain.find("://") {
+            Some(ind) => &domain[ind + 3..],
+            None => domain
+        }.trim_right_matches('/').to_string();
+        let mut source = HttpSource {
+            client: client,
+            domain: domain.to_string(),
+            host: host,
+            politeness: politeness.clone(),
+            limiter: RateLimiter::new(politeness.requests_per_sec, politeness.burst),
+            robots: Robots::allow_all()
+        };
+        if politeness.obey_robots {
+            source.robots = match source.fetch_with_retries("/robots.txt") {
+                Ok(text) => Robots::parse(&text, &politeness.user_agent),
+                Err(e) => {
+                    log!("Info", "No usable robots.txt for {} ({}), assuming everything is allowed", source.host, e);
+                    Robots::allow_all()
+                }
+            };
+        }
+        Ok(source)
+    }
+
+    fn attempt(&self, url: &str) -> Attempt {
+        self.limiter.acquire(&self.host);
+        let request = self.client.get(&(self.domain.clone() + &link::encode(url)))
+            .header(UserAgent(self.politeness.user_agent.clone()))
+            .send();
+        let mut resp = match request {
+            Ok(x) => x,
+            // Connection problems and timeouts
+            Err(e) => return Attempt::Retry(io::Error::new(io::ErrorKind::Other, format!("{}", e)), None)
+        };
+        if resp.status.is_success() {
+            let mut html = String::new();
+            return match resp.read_to_string(&mut html) {
+                Ok(_) => Attempt::Done(html),
+                Err(e) => Attempt::Retry(e, None)
+            };
+        }
+        let code = resp.status.to_u16();
+        let e = io::Error::new(io::ErrorKind::Other, format!("server responded with {}", resp.status));
+        if code == 429 || code >= 500 {
+            // Only the delay-seconds form of Retry-After is understood
+            let retry_after = resp.headers.get_raw("Retry-After")
+                .and_then(|x| x.first())
+                .and_then(|x| str::from_utf8(x).ok())
+                .and_then(|x| x.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            Attempt::Retry(e, retry_after)
+        } else {
+            Attempt::Fail(e)
+        }
+    }
+
+    fn fetch_with_retries(&self, url: &str) -> Result<String, io::Error> {
+        let mut attempt = 0;
+        loop {
+            match self.attempt(url) {
+                Attempt::Done(html) => return Ok(html),
+                Attempt::Fail(e) => return Err(e),
+                Attempt::Retry(e, retry_after) => {
+                    if attempt >= self.politeness.max_retries {
+                        return Err(io::Error::new(e.kind(), format!("{} (gave up after {} retries)", e, attempt)));
+                    }
+                    let wait = retry_after.unwrap_or(self.politeness.backoff(attempt));
+                    thread::sleep(wait);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+impl PageSource for HttpSource {
+    fn fetch(&self, url: &str) -> Result<String, io::Error> {
+        if !self.robots.allowed(&link::encode(url)) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "disallowed by robots.txt"));
+        }
+        self.fetch_with_retries(url)
+    }
+}
+
+/// Reads pages from a directory of saved html files, e.g. a local mirror or a fixture corpus.
+/// The page "/wiki/Astronomy" is looked for at "<root>/wiki/Astronomy", then "<root>/wiki/Astronomy.html".
+pub struct DirectorySource {
+    root: String
+}
+
+impl DirectorySource {
+    pub fn new(root: &str) -> DirectorySource {
+        DirectorySource { root: root.trim_right_matches('/').to_string() }
+    }
+}
+
+impl PageSource for DirectorySource {
+    fn fetch(&self, url: &str) -> Result<String, io::Error> {
+        let base = self.root.clone() + url;
+        let path = if Path::new(&base).is_file() { base } else { base + ".html" };
+        let mut file;
+        check!(File::open(&path), file);
+        let mut html = String::new();
+        check!(file.read_to_string(&mut html));
+        Ok(html)
+    }
+}
+
+/// Serves pages out of an in memory map of url to html. Mostly useful for testing the crawler.
+pub struct MemorySource {
+    pages: HashMap<String, String>
+}
+
+impl MemorySource {
+    pub fn new() -> MemorySource {
+        MemorySource { pages: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, url: &str, html: &str) {
+        self.pages.insert(url.to_string(), html.to_string());
+    }
+}
+
+impl PageSource for MemorySource {
+    fn fetch(&self, url: &str) -> Result<String, io::Error> {
+        match self.pages.get(url) {
+            Some(html) => Ok(html.clone()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no page for \"{}\"", url)))
+        }
+    }
+}
@@ -26,7 +26,7 @@ impl<'a, K, V> Serialize for PersistableHashMap<'a, K, V>
         let mut hashmap = HashMap::new();
         let len;
         check!(u64::deserialize(read), len);
-        for i in 0..len {
+        for _ in 0..len {
             let k;
             check!(K::deserialize(read), k);
             let v;
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{ Duration, Instant };
+use std::cmp;
+
+pub const DEFAULT_USER_AGENT: &'static str = "WikipediaReader/0.1 (https://github.com/jkarns275/Wikipedia-Reader)";
+
+/// Settings for how gently the crawler treats the servers it talks to.
+#[derive(Clone)]
+pub struct Politeness {
+    /// Sent with every request, and used to find our group in robots.txt
+    pub user_agent: String,
+
+    /// The sustained number of requests per second allowed to a single host.
+    pub requests_per_sec: f64,
+
+    /// How many requests may be made to a host in a burst before rate limiting kicks in.
+    pub burst: f64,
+
+    /// How many times a request that failed with a 429, 5xx or timeout is retried before giving up.
+    pub max_retries: u32,
+
+    /// How long to wait for a response before treating the request as timed out.
+    pub timeout: Duration,
+
+    /// Whether to skip pages disallowed by the host's robots.txt
+    pub obey_robots: bool
+}
+
+impl Politeness {
+    pub fn new() -> Politeness {
+        Politeness {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            requests_per_sec: 10.0,
+            burst: 10.0,
+            max_retries: 5,
+            timeout: Duration::from_secs(30),
+            obey_robots: true
+        }
+    }
+
+    /// How long to wait before retry number attempt (starting from 0): half a second, doubling each time, up to a minute.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let millis = 500u64.saturating_mul(1 << cmp::min(attempt, 16));
+        Duration::from_millis(cmp::min(millis, 60000))
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant
+}
+
+/// A token bucket per host. Each request takes a token; tokens refill at a fixed rate up to the burst size.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> RateLimiter {
+        RateLimiter { rate: rate, burst: burst.max(1.0), buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Blocks until a request to host is allowed.
+    pub fn acquire(&self, host: &str) {
+        if self.rate <= 0.0 { return }
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap();
+                let burst = self.burst;
+                let bucket = buckets.entry(host.to_string()).or_insert(Bucket { tokens: burst, last: Instant::now() });
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last);
+                let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+                bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+                bucket.last = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                (1.0 - bucket.tokens) / self.rate
+            };
+            thread::sleep(Duration::from_millis((wait * 1000.0).ceil() as u64));
+        }
+    }
+}
+
+/// The Allow / Disallow rules from a robots.txt that apply to us.
+pub struct Robots {
+    /// (path pattern, allowed)
+    rules: Vec<(String, bool)>
+}
+
+impl Robots {
+    /// Robots that allow everything, used when a host has no robots.txt
+    pub fn allow_all() -> Robots {
+        Robots { rules: vec![] }
+    }
+
+    /// Parses a robots.txt, keeping the rules of the group for user_agent, or the * group if there is none.
+    pub fn parse(text: &str, user_agent: &str) -> Robots {
+        // The product token, e.g. "wikipediareader" out of "WikipediaReader/0.1 (...)"
+        let token = user_agent.split(['/', ' ']).next().unwrap_or("").to_lowercase();
+        let mut ours = vec![];
+        let mut star = vec![];
+        let mut found_ours = false;
+
+        let mut agents: Vec<String> = vec![];
+        let mut in_rules = false;
+        let mut rules: Vec<(String, bool)> = vec![];
+        let mut lines = text.lines().map(|x| x.to_string()).collect::<Vec<String>>();
+        // A trailing user-agent line flushes the last group
+        lines.push("user-agent: ".to_string());
+        for line in lines.iter() {
+            let line = match line.find('#') {
+                Some(ind) => &line[..ind],
+                None => &line[..]
+            };
+            let ind = match line.find(':') {
+                Some(x) => x,
+                None => continue
+            };
+            let key = line[..ind].trim().to_lowercase();
+            let value = line[ind + 1..].trim().to_string();
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        for agent in agents.iter() {
+                            if !token.is_empty() && agent.contains(&token) {
+                                ours.extend(rules.iter().cloned());
+                                found_ours = true;
+                            } else if agent == "*" {
+                                star.extend(rules.iter().cloned());
+                            }
+                        }
+                        agents.clear();
+                        rules.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                },
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if !value.is_empty() {
+                        rules.push((value, key == "allow"));
+                    }
+                },
+                _ => {}
+            }
+        }
+        Robots { rules: if found_ours { ours } else { star } }
+    }
+
+    /// Whether path may be fetched. The longest matching rule wins, Allow wins ties.
+    pub fn allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for &(ref pattern, allow) in self.rules.iter() {
+            if pattern_matches(pattern, path) {
+                best = match best {
+                    Some((len, a)) if len > pattern.len() || (len == pattern.len() && a) => Some((len, a)),
+                    _ => Some((pattern.len(), allow))
+                };
+            }
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+}
+
+/// Matches a robots.txt path pattern against a path. Patterns are prefixes, except that * matches
+/// any run of characters and a trailing $ anchors the pattern to the end of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false)
+    };
+    let parts = pattern.split('*').collect::<Vec<&str>>();
+    if !path.starts_with(parts[0]) { return false }
+    let mut pos = parts[0].len();
+    for i in 1..parts.len() {
+        let part = parts[i];
+        if i == parts.len() - 1 && anchored {
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(ind) => pos += ind + part.len(),
+            None => return false
+        }
+    }
+    !anchored || pos == path.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &'static str = "\
+User-agent: *
+Disallow: /w/
+Allow: /w/load.php
+
+User-agent: WikipediaReader
+Disallow: /wiki/Special:
+Disallow: /*.json$
+";
+
+    fn millis(since: Instant) -> u64 {
+        let elapsed = since.elapsed();
+        elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1000000
+    }
+
+    #[test]
+    fn our_group_is_used_over_the_star_group() {
+        let robots = Robots::parse(ROBOTS, DEFAULT_USER_AGENT);
+        assert!(!robots.allowed("/wiki/Special:Random"));
+        // Only the * group disallows /w/.
+        assert!(robots.allowed("/w/index.php"));
+
+        let other = Robots::parse(ROBOTS, "SomeOtherBot/1.0");
+        assert!(!other.allowed("/w/index.php"));
+        assert!(other.allowed("/wiki/Special:Random"));
+    }
+
+    #[test]
+    fn a_longer_allow_overrides_a_shorter_disallow() {
+        let robots = Robots::parse(ROBOTS, "SomeOtherBot/1.0");
+        assert!(robots.allowed("/w/load.php"));
+        assert!(robots.allowed("/w/load.php?modules=site"));
+        assert!(!robots.allowed("/w/api.php"));
+    }
+
+    #[test]
+    fn dollar_anchors_a_pattern_to_the_end_of_the_path() {
+        let robots = Robots::parse(ROBOTS, DEFAULT_USER_AGENT);
+        assert!(!robots.allowed("/api/data.json"));
+        assert!(robots.allowed("/api/data.json?page=2"));
+
+        assert!(pattern_matches("/*.php$", "/w/index.php"));
+        assert!(!pattern_matches("/*.php$", "/w/index.php5"));
+        assert!(pattern_matches("/wiki/", "/wiki/Alpha"));
+        assert!(!pattern_matches("/wiki/$", "/wiki/Alpha"));
+        assert!(pattern_matches("/a*b*c", "/axxbyyczz"));
+        assert!(!pattern_matches("/a*b*c", "/axxcyyb"));
+    }
+
+    #[test]
+    fn empty_disallow_and_missing_robots_allow_everything() {
+        assert!(Robots::parse("User-agent: *\nDisallow:\n", DEFAULT_USER_AGENT).allowed("/wiki/Alpha"));
+        assert!(Robots::allow_all().allowed("/w/index.php"));
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills_at_the_rate() {
+        // 5 requests a second is one every 200ms.
+        let limiter = RateLimiter::new(5.0, 2.0);
+        let start = Instant::now();
+        limiter.acquire("en.wikipedia.org");
+        limiter.acquire("en.wikipedia.org");
+        assert!(millis(start) < 100);
+        // Other hosts have buckets of their own.
+        limiter.acquire("de.wikipedia.org");
+        assert!(millis(start) < 100);
+
+        limiter.acquire("en.wikipedia.org");
+        assert!(millis(start) >= 150);
+
+        // An idle bucket fills back up to the burst size and no further.
+        thread::sleep(Duration::from_millis(1000));
+        let start = Instant::now();
+        limiter.acquire("en.wikipedia.org");
+        limiter.acquire("en.wikipedia.org");
+        assert!(millis(start) < 100);
+        limiter.acquire("en.wikipedia.org");
+        assert!(millis(start) >= 150);
+    }
+}
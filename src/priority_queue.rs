@@ -61,14 +61,11 @@ impl<T> PriorityQueue<T> where T: Ord {
 
     fn search(&self, item: &T) -> Option<usize> {
         //self.in_search(item, 0)
-        let mut ind = 0;
-        loop {
-            if self.arr[ind] == *item {
+        for (ind, x) in self.arr.iter().enumerate() {
+            if *x == *item {
                 return Some(ind)
-            } else if self.arr[ind] > *item {
+            } else if *x > *item {
                 return None
-            } else {
-                ind += 1;
             }
         }
         None
@@ -99,15 +96,12 @@ impl<T> PriorityQueue<T> where T: Ord {
 
     /// Checks if the queue contains the element
     pub fn contains(&self, item: &T) -> bool {
-        match self.search(item) {
-            Some(_) => true,
-            None => false
-        }
+        self.search(item).is_some()
     }
 
     /// Removes an element to the heap and ensures it is still a heap; if not it makes it so.
     pub fn poll(&mut self) -> Option<T> {
-        if self.arr.len() == 0 {
+        if self.arr.is_empty() {
             None
         } else if self.arr.len() == 1 {
             self.arr.pop()
@@ -120,9 +114,14 @@ impl<T> PriorityQueue<T> where T: Ord {
         }
     }
 
+    /// Returns the smallest element without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        self.arr.first()
+    }
+
     /// Returns true of the queue is empty, otherwise false.
     pub fn empty(&self) -> bool {
-        self.arr.len() == 0
+        self.arr.is_empty()
     }
 }
 
@@ -145,7 +144,7 @@ impl Ord for GraphNode {
 
 impl PartialOrd for GraphNode {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.1.partial_cmp(&other.1)
+        Some(self.cmp(other))
     }
 }
 
@@ -160,18 +159,11 @@ impl PriorityQueue<GraphNode> {
 
     /// A really awful search function :)
     fn node_search(&self, node: &GraphNode) -> Option<usize> {
-        for i in 0..self.arr.len() {
-            if self.arr[i] == *node {
-                return Some(i);
-            }
-        }
-        return None;
+        self.arr.iter().position(|x| *x == *node)
     }
 
     pub fn decrease_priority(&mut self, node: usize, new_value: f64) -> Option<()> {
-        let index = self.node_search(&GraphNode(node, 0.0));
-        if index.is_some() {
-            let index = index.unwrap();
+        if let Some(index) = self.node_search(&GraphNode(node, 0.0)) {
             if self.arr[index].1 > new_value {
                 self.arr[index].1 = new_value;
                 self.adjust_after_decrease(index);
@@ -128,15 +128,15 @@ impl Serialize for Node {
         let mut keys = [0u64; NUM_ENTRIES];
         let mut children = [0u64; NUM_CHILDREN];
 
-        for i in 0..NUM_ENTRIES {
+        for key in keys.iter_mut() {
             let res;
             check!(u64::deserialize(from), res);
-            keys[i] = res;
+            *key = res;
         }
-        for i in 0..NUM_CHILDREN {
+        for child in children.iter_mut() {
             let res;
             check!(u64::deserialize(from), res);
-            children[i] = res;
+            *child = res;
         }
         let len;
         check!(u64::deserialize(from), len);
@@ -224,20 +224,41 @@ impl<K, V> PTree<K, V> where K: Serialize + Eq + PartialOrd + PartialEq + Debug,
         )
     }
 
+    /// Opens the tree at path, or creates a new one there if it can't be opened.
+    pub fn open_or_new(path: &str) -> Result<PTree<K, V>, io::Error> {
+        match Self::open(path) {
+            Ok(x) => Ok(x),
+            Err(_) => Self::new(path)
+        }
+    }
+
     // Since this tree doesn't support deleting of objects, reading keys from the keyfile until there is an error is actually a viable
     // way to get all of the keys. Probably a very frowned upon way though.
     pub fn keys(&mut self) -> Result<Vec<K::DeserializeOutput>, io::Error> {
         let mut keys = vec![];
         check!(self.keyfile.seek(SeekFrom::Start(0)));
-        loop {
-            match K::deserialize(&mut self.keyfile) {
-                Ok(r) => keys.push(r),
-                Err(_) => break
-            }
+        while let Ok(r) = K::deserialize(&mut self.keyfile) {
+            keys.push(r);
         }
         Ok(keys)
     }
 
+    /// Returns true if nothing has been inserted into the tree.
+    pub fn is_empty(&mut self) -> Result<bool, io::Error> {
+        let root = self.root_location;
+        let node;
+        check!(self.read_node(root), node);
+        Ok(node.len == 0)
+    }
+
+    /// Flushes any buffered writes to the tree, key and value files so what has been inserted so far survives a crash.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        check!(self.treefile.flush());
+        check!(self.keyfile.flush());
+        check!(self.valfile.flush());
+        Ok(())
+    }
+
     fn split_child(&mut self, x: &mut Node, x_loc: u64, child: usize) -> Result<(), io::Error> {
         let mut y;
         check!(self.read_node(x.children[child]), y);
@@ -259,13 +280,13 @@ impl<K, V> PTree<K, V> where K: Serialize + Eq + PartialOrd + PartialEq + Debug,
         }
         let z_loc;
         check!(self.write_node(&z), z_loc);
-        x.children[child as usize + 1] = z_loc;
+        x.children[child + 1] = z_loc;
         for j in (child as i64 .. x.len as i64).rev() {
             x.entries[j as usize + 1] = x.entries[j as usize];
         }
         x.len += 1;
         x.entries[child] = y.entries[T - 1]; // Minus one because arrays are base zero etc.
-        check!(self.update_node(&x, x_loc));
+        check!(self.update_node(x, x_loc));
         check!(self.update_node(&y, y_loc));
         Ok(())
     }
@@ -328,7 +349,7 @@ impl<K, V> PTree<K, V> where K: Serialize + Eq + PartialOrd + PartialEq + Debug,
                 i -= 1;
                 if i >= 0 { check!(self.read_key(x.entries[i as usize]), k_i); }
             }
-            i = i + 1;
+            i += 1;
             let x_child_i;
             check!(self.read_node(x.children[i as usize]), x_child_i);
             if x_child_i.len == NUM_ENTRIES as u64 {
@@ -346,38 +367,51 @@ impl<K, V> PTree<K, V> where K: Serialize + Eq + PartialOrd + PartialEq + Debug,
     }
 
     pub fn contains_key(&mut self, k: &K::DeserializeOutput) -> Result<bool, io::Error> {
-        let root = self.root_location;
-        self.contains_key_rec(k, root)
+        let entry_loc;
+        check!(self.find_entry(k), entry_loc);
+        Ok(entry_loc.is_some())
     }
 
-    fn contains_key_rec(&mut self, k: &K::DeserializeOutput, pos: u64) -> Result<bool, io::Error> {
-        let x;
-        check!(self.read_node(pos), x);
-        if x.len == 0 { return Ok(false) }
-        let mut k_i: K::DeserializeOutput;
-        check!(self.read_key(x.entries[0]), k_i);
-        let mut i = 0;
-        while i < x.len && k > &k_i {
-            i += 1;
-            if i < x.len {
-                check!(self.read_key(x.entries[i as usize]), k_i);
-            }
+    pub fn search(&mut self, k: &K::DeserializeOutput) -> Result<Option<V::DeserializeOutput>, io::Error> {
+        let entry_loc;
+        check!(self.find_entry(k), entry_loc);
+        match entry_loc {
+            Some(entry_loc) => {
+                let ret;
+                check!(self.read_value(entry_loc), ret);
+                Ok(Some(ret))
+            },
+            None => Ok(None)
         }
-        if i < x.len && k == &k_i {
-            Ok(true)
-        } else if x.leaf {
-            Ok(false)
-        } else {
-            self.contains_key_rec(k, x.children[i as usize])
+    }
+
+    /// Points k at the value v, replacing its current value if k is already in the tree, or inserting it otherwise.
+    /// The new value is appended to the value file and the old one's bytes are left behind in it: nothing reclaims
+    /// them, so a tree whose values are replaced often keeps growing.
+    pub fn insert_or_replace(&mut self, k: &K::DeserializeOutput, v: &V::DeserializeOutput) -> Result<(), io::Error> {
+        let entry_loc;
+        check!(self.find_entry(k), entry_loc);
+        match entry_loc {
+            Some(entry_loc) => {
+                let mut entry;
+                check!(self.read_entry(entry_loc), entry);
+                let val_pos;
+                check!(self.write_val(v), val_pos);
+                entry.value = val_pos;
+                check!(self.treefile.seek(SeekFrom::Start(entry_loc)));
+                entry.serialize(&mut self.treefile)
+            },
+            None => self.insert(k, v)
         }
     }
 
-    pub fn search(&mut self, k: &K::DeserializeOutput) -> Result<Option<V::DeserializeOutput>, io::Error> {
+    /// Finds the disk position of the Entry for k, if k is in the tree.
+    fn find_entry(&mut self, k: &K::DeserializeOutput) -> Result<Option<u64>, io::Error> {
         let root = self.root_location;
-        self.search_rec(k, root)
+        self.find_entry_rec(k, root)
     }
 
-    fn search_rec(&mut self, k: &K::DeserializeOutput, pos: u64) -> Result<Option<V::DeserializeOutput>, io::Error> {
+    fn find_entry_rec(&mut self, k: &K::DeserializeOutput, pos: u64) -> Result<Option<u64>, io::Error> {
         let x;
         check!(self.read_node(pos), x);
         if x.len == 0 { return Ok(None); }
@@ -392,13 +426,11 @@ impl<K, V> PTree<K, V> where K: Serialize + Eq + PartialOrd + PartialEq + Debug,
             }
         }
         if i < x.len && k == &k_i {
-            let ret;
-            check!(self.read_value(x.entries[i as usize]), ret);
-            Ok(Some(ret))
+            Ok(Some(x.entries[i as usize]))
         } else if x.leaf {
             Ok(None)
         } else {
-            self.search_rec(k, x.children[i as usize])
+            self.find_entry_rec(k, x.children[i as usize])
         }
     }
 
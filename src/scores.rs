@@ -0,0 +1,69 @@
+use cfile_rs::CFile;
+use graph::Graph;
+use random_access_file::Serialize;
+use std::io;
+use std::io::{ Read, Write };
+
+/// A score for every node of a graph (e.g. its PageRank), indexed like the nodes, kept on disk next to the graph.
+pub struct Scores {
+    pub values: Vec<f64>,
+    /// The fingerprint of the graph the scores were computed on, to notice when it has been rebuilt.
+    graph: u64
+}
+
+impl Scores {
+    pub fn new(graph: &Graph, values: Vec<f64>) -> Scores {
+        Scores { values: values, graph: graph.fingerprint() }
+    }
+
+    /// Returns true if the scores were computed on graph, going by its fingerprint. Scores from the same pages
+    /// rebuilt into a new graph would belong to other nodes.
+    pub fn fits(&self, graph: &Graph) -> bool {
+        self.values.len() == graph.count() && self.graph == graph.fingerprint()
+    }
+
+    pub fn persist(&self, to: &str) -> Result<(), io::Error> {
+        let mut file;
+        check!(CFile::open(to, "w+"), file);
+        check!(self.serialize(&mut file));
+        check!(file.flush());
+        Ok(())
+    }
+
+    pub fn from_disk(from: &str) -> Result<Scores, io::Error> {
+        let mut file;
+        check!(CFile::open(from, "r+"), file);
+        Scores::deserialize(&mut file)
+    }
+}
+
+impl Serialize for Scores {
+    type DeserializeOutput = Scores;
+
+    fn deserialize(from: &mut Read) -> Result<Scores, io::Error> {
+        let graph;
+        check!(u64::deserialize(from), graph);
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut values = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let value;
+            check!(f64::deserialize(from), value);
+            values.push(value);
+        }
+        Ok(Scores { values: values, graph: graph })
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.graph.serialize(to));
+        check!(self.values.len().serialize(to));
+        for value in self.values.iter() {
+            check!(value.serialize(to));
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self) -> u64 {
+        16 + 8 * self.values.len() as u64
+    }
+}
@@ -0,0 +1,168 @@
+use corpus::CorpusIndex;
+use link::Link;
+use page::Page;
+use std::collections::{ HashMap, HashSet };
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The names of the metrics metric_by_name knows.
+pub const METRICS: [&'static str; 7] = ["cosine", "jaccard", "tfidf", "bm25", "cocitation", "uniform", "anchor"];
+
+/// Decides how far apart two linked pages are, i.e. the weight of the edge for a link.
+pub trait SimilarityMetric {
+    /// The name the metric is picked by and recorded under.
+    fn name(&self) -> &'static str;
+
+    /// How similar the page from is to the page to, which it links to with link. Between 0 and 1, the closer to 1
+    /// the more similar.
+    fn similarity(&self, from: &Page, link: &Link, to: &Page) -> f64;
+
+    /// The weight of the edge for link. Lower weights are closer.
+    fn weight(&self, from: &Page, link: &Link, to: &Page) -> f64 {
+        1.1 - self.similarity(from, link, to)
+    }
+
+    /// The weights of the edges for every link out of from, along with the page each leads to. Metrics that can
+    /// compare one page against many at once override this.
+    fn weights(&self, from: &Page, targets: &[(&Link, &Page)]) -> Vec<f64> {
+        targets.iter().map(|&(link, to)| self.weight(from, link, to)).collect()
+    }
+}
+
+/// Cosine similarity of raw word counts.
+pub struct Cosine;
+
+impl SimilarityMetric for Cosine {
+    fn name(&self) -> &'static str { "cosine" }
+
+    fn similarity(&self, from: &Page, _link: &Link, to: &Page) -> f64 {
+        from.word_freq.compare(&to.word_freq)
+    }
+
+    fn weights(&self, from: &Page, targets: &[(&Link, &Page)]) -> Vec<f64> {
+        from.word_freq.compare_all(targets.iter().map(|&(_, to)| &to.word_freq)).into_iter().map(|x| 1.1 - x).collect()
+    }
+}
+
+fn jaccard<T: ::std::hash::Hash + Eq>(a: &HashSet<T>, b: &HashSet<T>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 { 0.0 } else { a.intersection(b).count() as f64 / union as f64 }
+}
+
+/// The share of words used by either page that are used by both.
+pub struct Jaccard;
+
+impl SimilarityMetric for Jaccard {
+    fn name(&self) -> &'static str { "jaccard" }
+
+    fn similarity(&self, from: &Page, _link: &Link, to: &Page) -> f64 {
+        let mut both = 0;
+        from.word_freq.join(&to.word_freq, |_, _, _| both += 1);
+        let either = from.word_freq.len() + to.word_freq.len() - both;
+        if either == 0 { 0.0 } else { both as f64 / either as f64 }
+    }
+}
+
+/// Cosine similarity of tf-idf vectors.
+pub struct TfIdf {
+    pub corpus: CorpusIndex
+}
+
+impl SimilarityMetric for TfIdf {
+    fn name(&self) -> &'static str { "tfidf" }
+
+    fn similarity(&self, from: &Page, _link: &Link, to: &Page) -> f64 {
+        self.corpus.tfidf_cosine(&from.word_freq, &to.word_freq)
+    }
+}
+
+/// Normalized BM25, see CorpusIndex::bm25_similarity
+pub struct Bm25 {
+    pub corpus: CorpusIndex
+}
+
+impl SimilarityMetric for Bm25 {
+    fn name(&self) -> &'static str { "bm25" }
+
+    fn similarity(&self, from: &Page, _link: &Link, to: &Page) -> f64 {
+        self.corpus.bm25_similarity(&from.word_freq, &to.word_freq)
+    }
+}
+
+/// Co-citation: pages are similar if the same pages link to both of them. This is the cosine similarity of
+/// the sets of pages linking to each.
+pub struct CoCitation {
+    inbound: HashMap<String, HashSet<String>>
+}
+
+impl CoCitation {
+    /// Collects the inbound links of pages. resolve maps a link's url to the url of the page it leads to.
+    pub fn new<'a, I, F>(pages: I, resolve: F) -> CoCitation
+        where I: Iterator<Item=&'a Page>, F: Fn(&str) -> String {
+        let mut inbound = HashMap::new();
+        for page in pages {
+            for link in page.links.iter() {
+                inbound.entry(resolve(&link.url)).or_insert(HashSet::new()).insert(page.url.clone());
+            }
+        }
+        CoCitation { inbound: inbound }
+    }
+}
+
+impl SimilarityMetric for CoCitation {
+    fn name(&self) -> &'static str { "cocitation" }
+
+    fn similarity(&self, from: &Page, _link: &Link, to: &Page) -> f64 {
+        match (self.inbound.get(&from.url), self.inbound.get(&to.url)) {
+            (Some(a), Some(b)) => a.intersection(b).count() as f64 / ((a.len() * b.len()) as f64).sqrt(),
+            _ => 0.0
+        }
+    }
+}
+
+/// Every edge weighs 1, so path lengths are hop counts.
+pub struct Uniform;
+
+impl SimilarityMetric for Uniform {
+    fn name(&self) -> &'static str { "uniform" }
+
+    fn similarity(&self, _from: &Page, _link: &Link, _to: &Page) -> f64 { 1.0 }
+
+    fn weight(&self, _from: &Page, _link: &Link, _to: &Page) -> f64 { 1.0 }
+}
+
+/// How well the text of a link describes the page it leads to: the overlap of the words in the link's anchor
+/// texts with the words in the title of the page.
+pub struct AnchorOverlap;
+
+fn words(text: &str) -> HashSet<String> {
+    text.unicode_words().map(|x| x.to_lowercase()).collect()
+}
+
+impl SimilarityMetric for AnchorOverlap {
+    fn name(&self) -> &'static str { "anchor" }
+
+    fn similarity(&self, _from: &Page, link: &Link, to: &Page) -> f64 {
+        let title = words(&to.title);
+        link.anchors.iter().map(|anchor| jaccard(&words(anchor), &title)).fold(0.0, f64::max)
+    }
+}
+
+/// Returns true if the metric needs a CorpusIndex over the cache.
+pub fn needs_corpus(name: &str) -> bool {
+    name == "tfidf" || name == "bm25"
+}
+
+/// Creates the metric called name. corpus is only used by metrics that need_corpus, and pages by cocitation.
+pub fn metric_by_name<'a, I, F>(name: &str, corpus: CorpusIndex, pages: I, resolve: F) -> Option<Box<SimilarityMetric>>
+    where I: Iterator<Item=&'a Page>, F: Fn(&str) -> String {
+    Some(match name {
+        "cosine" => Box::new(Cosine),
+        "jaccard" => Box::new(Jaccard),
+        "tfidf" => Box::new(TfIdf { corpus: corpus }),
+        "bm25" => Box::new(Bm25 { corpus: corpus }),
+        "cocitation" => Box::new(CoCitation::new(pages, resolve)),
+        "uniform" => Box::new(Uniform),
+        "anchor" => Box::new(AnchorOverlap),
+        _ => return None
+    })
+}
@@ -0,0 +1,152 @@
+use cfile_rs::CFile;
+use random_access_file::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::io::{ Read, Seek, SeekFrom, Write };
+
+/// Gives every term a small integer id, so frequency tables can store ids instead of repeating the words.
+/// Ids are handed out in order and never change, and the dictionary is saved next to the cache, which
+/// stores ids that only mean something together with it.
+pub struct TermDictionary {
+    ids: HashMap<String, u32>,
+    terms: Vec<String>,
+    /// The file the dictionary was last read from or saved to, how many of the terms are in it and where they
+    /// end.
+    saved_to: Option<String>,
+    saved: usize,
+    saved_len: u64
+}
+
+impl TermDictionary {
+    pub fn new() -> TermDictionary {
+        TermDictionary { ids: HashMap::new(), terms: vec![], saved_to: None, saved: 0, saved_len: 0 }
+    }
+
+    /// The id of term, giving it a new one if it doesn't have one yet.
+    pub fn intern(&mut self, term: &str) -> u32 {
+        if let Some(&id) = self.ids.get(term) {
+            return id;
+        }
+        let id = self.terms.len() as u32;
+        self.terms.push(term.to_string());
+        self.ids.insert(term.to_string(), id);
+        id
+    }
+
+    /// The id of term, if it has one.
+    pub fn id(&self, term: &str) -> Option<u32> {
+        self.ids.get(term).copied()
+    }
+
+    pub fn term(&self, id: u32) -> Option<&str> {
+        self.terms.get(id as usize).map(|x| x.as_str())
+    }
+
+    pub fn len(&self) -> usize { self.terms.len() }
+
+    /// Saves the dictionary to to. Terms are never renumbered, so if it was last saved to (or read from) the same
+    /// file only the terms added since are written, which makes saving before every cache insert cheap.
+    pub fn persist(&mut self, to: &str) -> Result<(), io::Error> {
+        let appending = self.saved_to.as_ref().is_some_and(|x| x == to);
+        if appending && self.saved == self.terms.len() { return Ok(()) }
+        let mut file;
+        if appending {
+            check!(CFile::open(to, "r+"), file);
+            // Not the end of the file, which may have half a term on it from a save that was cut short.
+            check!(file.seek(SeekFrom::Start(self.saved_len)));
+            for term in self.terms[self.saved..].iter() {
+                check!(term.serialize(&mut file));
+            }
+            // The count is written last, so a file cut short still reads as the terms it had before.
+            check!(file.flush());
+            check!(file.seek(SeekFrom::Start(0)));
+            check!(self.terms.len().serialize(&mut file));
+        } else {
+            check!(CFile::open(to, "w+"), file);
+            check!(self.serialize(&mut file));
+        }
+        check!(file.flush());
+        self.saved_to = Some(to.to_string());
+        self.saved = self.terms.len();
+        self.saved_len = self.serialized_len();
+        Ok(())
+    }
+
+    pub fn from_disk(from: &str) -> Result<TermDictionary, io::Error> {
+        let mut file;
+        check!(CFile::open(from, "r+"), file);
+        let mut dictionary;
+        check!(TermDictionary::deserialize(&mut file), dictionary);
+        dictionary.saved_to = Some(from.to_string());
+        dictionary.saved = dictionary.terms.len();
+        dictionary.saved_len = dictionary.serialized_len();
+        Ok(dictionary)
+    }
+}
+
+impl Serialize for TermDictionary {
+    type DeserializeOutput = TermDictionary;
+
+    fn deserialize(from: &mut Read) -> Result<TermDictionary, io::Error> {
+        let len;
+        check!(u64::deserialize(from), len);
+        let mut dictionary = TermDictionary::new();
+        for _ in 0..len {
+            let term;
+            check!(String::deserialize(from), term);
+            dictionary.intern(&term);
+        }
+        Ok(dictionary)
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!(self.terms.len().serialize(to));
+        for term in self.terms.iter() {
+            check!(term.serialize(to));
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self) -> u64 {
+        self.terms.iter().fold(8, |len, x| len + x.serialized_len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn ids_stay_the_same_when_the_dictionary_is_reopened() {
+        let path = env::temp_dir().join(format!("crate-test-terms-{}", process::id())).to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+
+        let mut terms = TermDictionary::new();
+        let alpha = terms.intern("alpha");
+        let beta = terms.intern("beta");
+        terms.persist(&path).unwrap();
+        // Appended to the file saved above.
+        let gamma = terms.intern("gamma");
+        terms.persist(&path).unwrap();
+
+        let mut reopened = TermDictionary::from_disk(&path).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert_eq!(reopened.id("alpha"), Some(alpha));
+        assert_eq!(reopened.id("beta"), Some(beta));
+        assert_eq!(reopened.id("gamma"), Some(gamma));
+        assert_eq!(reopened.intern("beta"), beta);
+        let delta = reopened.intern("delta");
+        assert_eq!(delta, 3);
+        reopened.persist(&path).unwrap();
+
+        let reopened = TermDictionary::from_disk(&path).unwrap();
+        assert_eq!(reopened.len(), 4);
+        assert_eq!(reopened.term(alpha), Some("alpha"));
+        assert_eq!(reopened.term(gamma), Some("gamma"));
+        assert_eq!(reopened.term(delta), Some("delta"));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,232 @@
+use cfile_rs::CFile;
+use random_access_file::Serialize;
+use regex::Regex;
+use rust_stemmers::{ Algorithm, Stemmer };
+use std::collections::HashSet;
+use std::io;
+use std::io::{ Read, Write };
+use std::sync::{ Mutex, MutexGuard };
+use term_dictionary::TermDictionary;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+lazy_static! {
+    /// Citation markers and edit links that show up in the text of paragraphs and headings, e.g. "[12]" or "[edit]"
+    static ref MARKER_REGEX: Regex = Regex::new(r#"\[(\d+|[a-z]|citation needed|edit)\]"#).unwrap();
+
+    static ref STOP_WORDS: HashSet<&'static str> = [
+        "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+        "be", "because", "been", "before", "being", "below", "between", "both", "but", "by", "can", "could", "did", "do",
+        "does", "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has", "have", "having", "he",
+        "her", "here", "hers", "herself", "him", "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its",
+        "itself", "just", "may", "me", "might", "more", "most", "must", "my", "myself", "no", "nor", "not", "now", "of",
+        "off", "on", "once", "only", "or", "other", "our", "ours", "ourselves", "out", "over", "own", "same", "she",
+        "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them", "themselves", "then", "there",
+        "these", "they", "this", "those", "through", "to", "too", "under", "until", "up", "very", "was", "we", "were",
+        "what", "when", "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours",
+        "yourself", "yourselves"
+    ].iter().cloned().collect();
+}
+
+/// Removes citation markers and edit links from text.
+pub fn strip_markers(text: &str) -> String {
+    MARKER_REGEX.replace_all(text, "").into_owned()
+}
+
+/// Looks up the snowball stemmer for a language, e.g. "english"
+pub fn stemmer_algorithm(language: &str) -> Option<Algorithm> {
+    Some(match language.to_lowercase().as_str() {
+        "arabic" => Algorithm::Arabic,
+        "danish" => Algorithm::Danish,
+        "dutch" => Algorithm::Dutch,
+        "english" => Algorithm::English,
+        "finnish" => Algorithm::Finnish,
+        "french" => Algorithm::French,
+        "german" => Algorithm::German,
+        "greek" => Algorithm::Greek,
+        "hungarian" => Algorithm::Hungarian,
+        "italian" => Algorithm::Italian,
+        "norwegian" => Algorithm::Norwegian,
+        "portuguese" => Algorithm::Portuguese,
+        "romanian" => Algorithm::Romanian,
+        "russian" => Algorithm::Russian,
+        "spanish" => Algorithm::Spanish,
+        "swedish" => Algorithm::Swedish,
+        "tamil" => Algorithm::Tamil,
+        "turkish" => Algorithm::Turkish,
+        _ => return None
+    })
+}
+
+/// Settings for how text is split into the terms that are counted in a FrequencyTable. The pages in a cache
+/// all have to be tokenized the same way to be comparable, so these are saved next to the cache.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TokenizerConfig {
+    pub lowercase: bool,
+
+    /// Whether to drop common english words like "the" and "of"
+    pub stop_words: bool,
+
+    /// Words shorter than this (in characters) are dropped.
+    pub min_len: usize,
+
+    /// The language to stem words in, or None to leave words as they are.
+    pub stem: Option<String>
+}
+
+impl TokenizerConfig {
+    pub fn new() -> TokenizerConfig {
+        TokenizerConfig {
+            lowercase: true,
+            stop_words: true,
+            min_len: 2,
+            stem: None
+        }
+    }
+
+    pub fn persist(&self, to: &str) -> Result<(), io::Error> {
+        let mut file;
+        check!(CFile::open(to, "w+"), file);
+        check!(self.serialize(&mut file));
+        check!(file.flush());
+        Ok(())
+    }
+
+    pub fn from_disk(from: &str) -> Result<TokenizerConfig, io::Error> {
+        let mut file;
+        check!(CFile::open(from, "r+"), file);
+        TokenizerConfig::deserialize(&mut file)
+    }
+}
+
+impl Serialize for TokenizerConfig {
+    type DeserializeOutput = TokenizerConfig;
+
+    fn deserialize(from: &mut Read) -> Result<TokenizerConfig, io::Error> {
+        let lowercase;
+        check!(u8::deserialize(from), lowercase);
+        let stop_words;
+        check!(u8::deserialize(from), stop_words);
+        let min_len;
+        check!(u64::deserialize(from), min_len);
+        let stem;
+        check!(String::deserialize(from), stem);
+        Ok(TokenizerConfig {
+            lowercase: lowercase != 0,
+            stop_words: stop_words != 0,
+            min_len: min_len as usize,
+            stem: if stem.is_empty() { None } else { Some(stem) }
+        })
+    }
+
+    fn serialize(&self, to: &mut Write) -> Result<(), io::Error> {
+        check!((self.lowercase as u8).serialize(to));
+        check!((self.stop_words as u8).serialize(to));
+        check!((self.min_len as u64).serialize(to));
+        self.stem.clone().unwrap_or_default().serialize(to)
+    }
+
+    fn serialized_len(&self) -> u64 {
+        1 + 1 + 8 + self.stem.clone().unwrap_or_default().serialized_len()
+    }
+}
+
+/// Splits text into terms: citation markers are removed, the text is NFC normalized and split into words
+/// on unicode word boundaries (which drops punctuation), and then words are lowercased, filtered and stemmed
+/// according to the config. Terms are interned in the tokenizer's TermDictionary.
+pub struct Tokenizer {
+    pub config: TokenizerConfig,
+    stemmer: Option<Stemmer>,
+    terms: Mutex<TermDictionary>
+}
+
+impl Tokenizer {
+    pub fn new(config: TokenizerConfig) -> Result<Tokenizer, io::Error> {
+        Tokenizer::with_terms(config, TermDictionary::new())
+    }
+
+    /// A tokenizer that gives terms the ids they have in terms.
+    pub fn with_terms(config: TokenizerConfig, terms: TermDictionary) -> Result<Tokenizer, io::Error> {
+        let stemmer = match config.stem {
+            Some(ref language) => match stemmer_algorithm(language) {
+                Some(algorithm) => Some(Stemmer::create(algorithm)),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no stemmer for \"{}\"", language)))
+            },
+            None => None
+        };
+        Ok(Tokenizer { config: config, stemmer: stemmer, terms: Mutex::new(terms) })
+    }
+
+    pub fn tokens(&self, text: &str) -> Vec<String> {
+        let text = strip_markers(text).nfc().collect::<String>();
+        let mut tokens = vec![];
+        for word in text.unicode_words() {
+            let word = if self.config.lowercase { word.to_lowercase() } else { word.to_string() };
+            if word.chars().count() < self.config.min_len { continue }
+            if self.config.stop_words && STOP_WORDS.contains(word.to_lowercase().as_str()) { continue }
+            match self.stemmer {
+                Some(ref stemmer) => tokens.push(stemmer.stem(&word).into_owned()),
+                None => tokens.push(word)
+            }
+        }
+        tokens
+    }
+
+    /// The ids of the terms in text, interning terms that haven't been seen before.
+    pub fn term_ids(&self, text: &str) -> Vec<u32> {
+        let tokens = self.tokens(text);
+        let mut terms = self.terms();
+        tokens.iter().map(|x| terms.intern(x)).collect()
+    }
+
+    pub fn terms(&self) -> MutexGuard<'_, TermDictionary> {
+        self.terms.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_tokens_drop_markers_stop_words_and_short_words() {
+        let tokenizer = Tokenizer::new(TokenizerConfig::new()).unwrap();
+        assert_eq!(tokenizer.tokens("The Star, stars[1] and the star's É[citation needed]"), vec!["star", "stars", "star's"]);
+        // A decomposed é is NFC normalized before it's counted.
+        assert_eq!(tokenizer.tokens("Cafe\u{301} café"), vec!["café", "café"]);
+    }
+
+    #[test]
+    fn tokens_follow_the_config() {
+        let mut config = TokenizerConfig::new();
+        config.stem = Some("english".to_string());
+        config.min_len = 1;
+        let tokenizer = Tokenizer::new(config).unwrap();
+        assert_eq!(tokenizer.tokens("Stars, star. Running É"), vec!["star", "star", "run", "é"]);
+
+        let mut config = TokenizerConfig::new();
+        config.lowercase = false;
+        config.stop_words = false;
+        let tokenizer = Tokenizer::new(config).unwrap();
+        assert_eq!(tokenizer.tokens("The Sun and the Moon"), vec!["The", "Sun", "and", "the", "Moon"]);
+
+        let mut config = TokenizerConfig::new();
+        config.stem = Some("klingon".to_string());
+        assert!(Tokenizer::new(config).is_err());
+    }
+
+    #[test]
+    fn config_survives_serialization() {
+        let mut config = TokenizerConfig::new();
+        config.stem = Some("german".to_string());
+        config.stop_words = false;
+        let mut buf = vec![];
+        config.serialize(&mut buf).unwrap();
+        assert_eq!(buf.len() as u64, config.serialized_len());
+        assert_eq!(TokenizerConfig::deserialize(&mut &buf[..]).unwrap(), config);
+
+        let mut buf = vec![];
+        TokenizerConfig::new().serialize(&mut buf).unwrap();
+        assert_eq!(TokenizerConfig::deserialize(&mut &buf[..]).unwrap().stem, None);
+    }
+}